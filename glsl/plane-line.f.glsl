@@ -1,8 +1,26 @@
 precision mediump float;
 
 varying float v_opacity;
+varying vec3 v_color;
+varying float v_distance;
+varying float v_dash;
+
+// Length of one repeat of a dash pattern, in scene units
+const float PERIOD = 0.03;
+
+// Whether the point at `x` of the period is drawn: 0 solid, 1 dashed, 2 dash-dot, 3 dotted
+float dash(float pattern, float x) {
+    if (pattern < 0.5) {
+        return 1.0;
+    } else if (pattern < 1.5) {
+        return step(x, 0.6);
+    } else if (pattern < 2.5) {
+        return step(x, 0.55) + step(0.7, x) * step(x, 0.8);
+    }
+    return step(fract(x * 3.0), 0.4);
+}
 
 void main() {
-    vec3 color = vec3(0.6, 0.6, 0.6);
-    gl_FragColor = vec4(color * v_opacity, v_opacity);
+    float opacity = v_opacity * dash(v_dash, fract(v_distance / PERIOD));
+    gl_FragColor = vec4(v_color * opacity, opacity);
 }
\ No newline at end of file
@@ -4,10 +4,19 @@ uniform mat4 u_camera;
 
 attribute vec3 a_position;
 attribute float a_opacity;
+attribute vec3 a_color;
+attribute float a_distance;
+attribute float a_dash;
 
 varying float v_opacity;
+varying vec3 v_color;
+varying float v_distance;
+varying float v_dash;
 
 void main() {
     v_opacity = a_opacity;
+    v_color = a_color;
+    v_distance = a_distance;
+    v_dash = a_dash;
     gl_Position = u_camera * vec4(a_position, 1.0);
-}
+}
\ No newline at end of file
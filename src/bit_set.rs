@@ -1,57 +1,166 @@
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+use std::iter::FromIterator;
+
+const WORD_BITS: u32 = u32::BITS;
+
+/// Growable set of vertex indices
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
 pub struct BitSet {
-    data: u32,
+    data: Vec<u32>,
 }
 
 impl BitSet {
-    pub const fn with_bits(bits: &[u8]) -> BitSet {
-        let mut data = 0u32;
-        let mut i = 0;
-        // Using while to make the fn const
-        while i < bits.len() {
-            data |= 1 << bits[i];
-            i += 1;
+    pub fn new() -> BitSet {
+        BitSet { data: Vec::new() }
+    }
+
+    pub fn with_bits(bits: &[u32]) -> BitSet {
+        bits.iter().copied().collect()
+    }
+
+    /// Set of all indices from 0 to `size - 1`
+    pub fn full(size: u32) -> BitSet {
+        (0..size).collect()
+    }
+
+    pub fn has(&self, bit: u32) -> bool {
+        let word = (bit / WORD_BITS) as usize;
+        word < self.data.len() && (self.data[word] & (1u32 << (bit % WORD_BITS))) != 0
+    }
+
+    pub fn insert(&mut self, bit: u32) {
+        let word = (bit / WORD_BITS) as usize;
+        if word >= self.data.len() {
+            self.data.resize(word + 1, 0);
+        }
+        self.data[word] |= 1u32 << (bit % WORD_BITS);
+    }
+
+    pub fn remove(&mut self, bit: u32) {
+        let word = (bit / WORD_BITS) as usize;
+        if word < self.data.len() {
+            self.data[word] &= !(1u32 << (bit % WORD_BITS));
+            self.trim();
         }
+    }
 
+    pub fn len(&self) -> usize {
+        self.data
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn iter(&self) -> BitSetIterator<'_> {
+        BitSetIterator::new(self)
+    }
+
+    pub fn union(&self, other: &BitSet) -> BitSet {
+        let (long, short) = if self.data.len() >= other.data.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut data = long.data.clone();
+        for (word, other) in data.iter_mut().zip(short.data.iter()) {
+            *word |= other;
+        }
         BitSet { data }
     }
-    pub fn has(self, bit: u8) -> bool {
-        (self.data & (1u32 << bit)) != 0
+
+    pub fn intersection(&self, other: &BitSet) -> BitSet {
+        let data = self
+            .data
+            .iter()
+            .zip(other.data.iter())
+            .map(|(a, b)| a & b)
+            .collect();
+        BitSet::trimmed(data)
+    }
+
+    pub fn difference(&self, other: &BitSet) -> BitSet {
+        let data = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(i, a)| a & !other.data.get(i).copied().unwrap_or(0))
+            .collect();
+        BitSet::trimmed(data)
+    }
+
+    /// Indices from 0 to `size - 1` that are not in the set,
+    /// use `model.vertices.len()` as size to get the rest of the model
+    pub fn complement(&self, size: u32) -> BitSet {
+        BitSet::full(size).difference(self)
+    }
+
+    fn trimmed(data: Vec<u32>) -> BitSet {
+        let mut set = BitSet { data };
+        set.trim();
+        set
+    }
+
+    // Keep no trailing zero words, so derived equality compares contents
+    fn trim(&mut self) {
+        while self.data.last() == Some(&0) {
+            self.data.pop();
+        }
+    }
+}
+
+impl FromIterator<u32> for BitSet {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        for bit in iter {
+            set.insert(bit);
+        }
+        set
     }
 }
 
-impl IntoIterator for BitSet {
-    type Item = u8;
-    type IntoIter = BitSetIterator;
+impl<'a> IntoIterator for &'a BitSet {
+    type Item = u32;
+    type IntoIter = BitSetIterator<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
         BitSetIterator::new(self)
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct BitSetIterator {
-    set: BitSet,
-    i: i8,
+#[derive(Clone)]
+pub struct BitSetIterator<'a> {
+    set: &'a BitSet,
+    word: usize,
+    bits: u32,
 }
 
-impl BitSetIterator {
-    fn new(set: BitSet) -> BitSetIterator {
-        BitSetIterator { set, i: -1 }
+impl<'a> BitSetIterator<'a> {
+    fn new(set: &'a BitSet) -> BitSetIterator<'a> {
+        BitSetIterator {
+            set,
+            word: 0,
+            bits: set.data.first().copied().unwrap_or(0),
+        }
     }
 }
 
-impl Iterator for BitSetIterator {
-    type Item = u8;
+impl<'a> Iterator for BitSetIterator<'a> {
+    type Item = u32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.i < 30 {
-            self.i += 1;
-            if self.set.has(self.i as u8) {
-                return Some(self.i as u8);
+        while self.bits == 0 {
+            self.word += 1;
+            if self.word >= self.set.data.len() {
+                return None;
             }
+            self.bits = self.set.data[self.word];
         }
-        None
+        let bit = self.bits.trailing_zeros();
+        self.bits &= self.bits - 1;
+        Some(self.word as u32 * WORD_BITS + bit)
     }
 }
 
@@ -89,7 +198,7 @@ mod tests {
     #[test]
     fn test_bit_set_iterator() {
         let set = BitSet::with_bits(&[2, 3, 5, 6, 7, 8, 9, 11]);
-        let mut iter = set.into_iter();
+        let mut iter = set.iter();
         assert_eq!(iter.next(), Some(2));
         assert_eq!(iter.next(), Some(3));
         assert_eq!(iter.next(), Some(5));
@@ -100,4 +209,33 @@ mod tests {
         assert_eq!(iter.next(), Some(11));
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_bit_set_growable() {
+        let set = BitSet::with_bits(&[0, 31, 32, 63, 64, 1000]);
+        assert_eq!(set.len(), 6);
+        assert!(set.has(31));
+        assert!(set.has(32));
+        assert!(!set.has(33));
+        assert!(set.has(1000));
+        assert!(!set.has(5000));
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![0, 31, 32, 63, 64, 1000]
+        );
+    }
+
+    #[test]
+    fn test_bit_set_algebra() {
+        let a = BitSet::with_bits(&[1, 2, 40, 100]);
+        let b = BitSet::with_bits(&[2, 3, 100]);
+
+        assert_eq!(a.union(&b), BitSet::with_bits(&[1, 2, 3, 40, 100]));
+        assert_eq!(a.intersection(&b), BitSet::with_bits(&[2, 100]));
+        assert_eq!(a.difference(&b), BitSet::with_bits(&[1, 40]));
+        assert_eq!(b.difference(&a), BitSet::with_bits(&[3]));
+        assert_eq!(b.complement(5), BitSet::with_bits(&[0, 1, 4]));
+        assert!(b.difference(&b).is_empty());
+        assert_eq!(b.difference(&b), BitSet::new());
+    }
 }
@@ -0,0 +1,35 @@
+use crate::model::timeline::Step;
+use std::cell::RefCell;
+use web_sys::HtmlElement;
+
+/// Label of the fold step being played, shown over the canvas
+pub struct Caption {
+    element: HtmlElement,
+    /// Text in the element, so the page is touched only when it changes
+    shown: RefCell<Option<String>>,
+}
+
+impl Caption {
+    pub fn new(element: HtmlElement) -> Caption {
+        element.set_text_content(None);
+        Caption {
+            element,
+            shown: RefCell::new(None),
+        }
+    }
+
+    /// Show the label of the `step` with the part of its motion done, or nothing.
+    /// A step switches the caption once the paper moves, so the label of the previous step
+    /// stays during the pause after it.
+    pub fn show(&self, step: Option<(&Step, f32)>) {
+        let label = match step {
+            Some((_, progress)) if progress <= 0.0 => return,
+            Some((step, _)) => step.label.as_deref(),
+            None => None,
+        };
+        if self.shown.borrow().as_deref() != label {
+            self.element.set_text_content(label);
+            *self.shown.borrow_mut() = label.map(str::to_string);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::camera::Camera;
+use crate::model::timeline::{Paused, Step, Timeline};
 use crate::orientation::Orientation;
 use crate::path::Path;
 use crate::smooth::smooth;
@@ -46,13 +47,19 @@ impl ControlPoint {
 
 const DEFAULT_FOV: f32 = 40.0;
 const DEFAULT_CAMERA_POSITION: Vec3 = Vec3::new(0.0, 0.0, -2.25);
+/// Pause of the folding after every step, in parts of the folding
+const STEP_PAUSE: f32 = 0.03;
 
 pub struct Flight {
     animation: Box<dyn Animated<ControlPoint, f32>>,
+    /// Fold steps in fold phases
+    steps: Timeline,
+    paused: Paused,
 }
 
 impl Flight {
-    pub fn new() -> Flight {
+    /// Flight of the plane folded by the `steps`, in fold phases from 0.0 to 1.0
+    pub fn new(steps: Timeline) -> Flight {
         let animation = keyframes::from(ControlPoint {
             plane_position: Vec3::new(0.0, 0.0, 0.0),
             plane_orientation: Orientation::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
@@ -186,10 +193,26 @@ impl Flight {
 
         Flight {
             animation: Box::new(animation),
+            paused: steps.paused(STEP_PAUSE),
+            steps,
         }
     }
 
     pub fn get(&self, t: f32) -> ControlPoint {
-        self.animation.get(t)
+        let point = self.animation.get(t);
+        ControlPoint {
+            fold_phase: self.paused.time(point.fold_phase),
+            ..point
+        }
+    }
+
+    /// Fold step playing at the `point` and the part of its motion done,
+    /// none once the plane is folded
+    pub fn step(&self, point: &ControlPoint) -> Option<(&Step, f32)> {
+        if point.fold_phase >= 1.0 {
+            return None;
+        }
+        let at = self.steps.at(point.fold_phase)?;
+        Some((&self.steps.steps()[at.position], at.progress))
     }
 }
@@ -3,11 +3,12 @@ extern crate nalgebra_glm as glm;
 use crate::plane::Plane;
 use crate::utils::set_panic_hook;
 use wasm_bindgen::prelude::*;
-use web_sys::HtmlCanvasElement;
+use web_sys::{HtmlCanvasElement, HtmlElement};
 
 mod background;
 mod bit_set;
 mod camera;
+mod caption;
 mod cover;
 mod flight;
 mod floor;
@@ -30,6 +31,10 @@ export function getCanvas() {
     return document.getElementById('canvas');
 }
 
+export function getCaption() {
+    return document.getElementById('caption');
+}
+
 export async function animationLoop(callback) {
     let startTime = performance.now();
     const container = document.getElementById('container');
@@ -69,11 +74,13 @@ extern "C" {
     fn animation_loop(callback: &Closure<dyn Fn(i32, i32, f32)>);
     #[wasm_bindgen(js_name = getCanvas)]
     fn get_canvas() -> HtmlCanvasElement;
+    #[wasm_bindgen(js_name = getCaption)]
+    fn get_caption() -> HtmlElement;
 }
 
 fn main() {
     set_panic_hook();
-    let plane = Plane::new(&get_canvas()).unwrap();
+    let plane = Plane::new(&get_canvas(), get_caption()).unwrap();
     let callback = Box::new(Closure::wrap(Box::new(move |w: i32, h: i32, phase: f32| {
         plane.render(w, h, phase);
     }) as Box<dyn Fn(i32, i32, f32)>));
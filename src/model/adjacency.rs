@@ -0,0 +1,266 @@
+use crate::model::Model;
+use std::collections::HashMap;
+
+/// Undirected edge with the half-edges of all triangles that have it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    /// Edge vertices, the lower index first
+    pub vertices: (u32, u32),
+    pub half_edges: Vec<usize>,
+}
+
+/// Half-edge connectivity of model triangles.
+/// Half-edge `3 * face + k` goes from the k-th vertex of the face to the next one,
+/// so faces and half-edges don't need separate tables.
+#[derive(Debug, Clone)]
+pub struct Adjacency {
+    half_edges: Vec<(u32, u32)>,
+    twins: Vec<Option<usize>>,
+    /// Undirected edges in the order they first appear in triangles
+    edges: Vec<Edge>,
+    edge_indexes: HashMap<(u32, u32), usize>,
+    /// Half-edges starting at every vertex
+    outgoing: Vec<Vec<usize>>,
+}
+
+impl Adjacency {
+    /// Build connectivity of the model triangles. Indexes must be in range,
+    /// see `Model::validate`. Edges of non-manifold or inconsistently wound
+    /// triangles have no twins, so they look like boundary edges.
+    pub fn new(model: &Model) -> Adjacency {
+        let mut half_edges = Vec::with_capacity(model.triangles.len() * 3);
+        let mut edges: Vec<Edge> = Vec::new();
+        let mut edge_indexes = HashMap::new();
+        let mut outgoing = vec![Vec::new(); model.vertices.len()];
+
+        for &(a, b, c) in model.triangles.iter() {
+            for &(from, to) in [(a, b), (b, c), (c, a)].iter() {
+                let h = half_edges.len();
+                half_edges.push((from, to));
+                outgoing[from as usize].push(h);
+
+                let key = (from.min(to), from.max(to));
+                let index = *edge_indexes.entry(key).or_insert_with(|| {
+                    edges.push(Edge {
+                        vertices: key,
+                        half_edges: Vec::new(),
+                    });
+                    edges.len() - 1
+                });
+                edges[index].half_edges.push(h);
+            }
+        }
+
+        let mut twins = vec![None; half_edges.len()];
+        for edge in edges.iter() {
+            if let [h1, h2] = edge.half_edges[..] {
+                if half_edges[h1].0 == half_edges[h2].1 {
+                    twins[h1] = Some(h2);
+                    twins[h2] = Some(h1);
+                }
+            }
+        }
+
+        Adjacency {
+            half_edges,
+            twins,
+            edges,
+            edge_indexes,
+            outgoing,
+        }
+    }
+
+    pub fn face(&self, half_edge: usize) -> usize {
+        half_edge / 3
+    }
+
+    #[allow(dead_code)]
+    pub fn next(&self, half_edge: usize) -> usize {
+        half_edge - half_edge % 3 + (half_edge + 1) % 3
+    }
+
+    pub fn prev(&self, half_edge: usize) -> usize {
+        half_edge - half_edge % 3 + (half_edge + 2) % 3
+    }
+
+    /// Start and end vertices of the half-edge
+    pub fn vertices(&self, half_edge: usize) -> (u32, u32) {
+        self.half_edges[half_edge]
+    }
+
+    /// Half-edge of the neighbour face going in the opposite direction
+    pub fn twin(&self, half_edge: usize) -> Option<usize> {
+        self.twins[half_edge]
+    }
+
+    /// Half-edges of the face, starting from its first vertex
+    pub fn face_half_edges(&self, face: usize) -> [usize; 3] {
+        [face * 3, face * 3 + 1, face * 3 + 2]
+    }
+
+    pub fn faces_count(&self) -> usize {
+        self.half_edges.len() / 3
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn edge(&self, a: u32, b: u32) -> Option<&Edge> {
+        self.edge_indexes
+            .get(&(a.min(b), a.max(b)))
+            .map(|&i| &self.edges[i])
+    }
+
+    /// Faces that have the edge between `a` and `b`
+    pub fn edge_faces(&self, a: u32, b: u32) -> Vec<usize> {
+        self.edge(a, b)
+            .map(|edge| edge.half_edges.iter().map(|&h| self.face(h)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Faces on the two sides of a manifold edge. The first face goes along the edge
+    /// from its lower vertex to the higher one, the second goes backwards.
+    pub fn sides(&self, edge: &Edge) -> Option<(usize, usize)> {
+        let h = edge.half_edges[0];
+        let twin = self.twin(h)?;
+        if self.vertices(h).0 == edge.vertices.0 {
+            Some((self.face(h), self.face(twin)))
+        } else {
+            Some((self.face(twin), self.face(h)))
+        }
+    }
+
+    /// Faces sharing an edge with the face
+    #[allow(dead_code)]
+    pub fn face_neighbours(&self, face: usize) -> Vec<usize> {
+        let mut faces: Vec<usize> = self
+            .face_half_edges(face)
+            .iter()
+            .flat_map(|&h| {
+                let (a, b) = self.vertices(h);
+                self.edge_faces(a, b)
+            })
+            .filter(|&f| f != face)
+            .collect();
+        faces.sort_unstable();
+        faces.dedup();
+        faces
+    }
+
+    /// Half-edges starting at the vertex, one in every face around it
+    pub fn vertex_half_edges(&self, vertex: u32) -> &[usize] {
+        &self.outgoing[vertex as usize]
+    }
+
+    /// Faces around the vertex
+    pub fn vertex_faces(&self, vertex: u32) -> Vec<usize> {
+        self.outgoing[vertex as usize]
+            .iter()
+            .map(|&h| self.face(h))
+            .collect()
+    }
+
+    /// Vertices connected to the vertex by an edge, sorted
+    #[allow(dead_code)]
+    pub fn vertex_neighbours(&self, vertex: u32) -> Vec<u32> {
+        let mut vertices: Vec<u32> = self.outgoing[vertex as usize]
+            .iter()
+            .flat_map(|&h| vec![self.vertices(h).1, self.vertices(self.prev(h)).0])
+            .filter(|&v| v != vertex)
+            .collect();
+        vertices.sort_unstable();
+        vertices.dedup();
+        vertices
+    }
+
+    /// Half-edges without twins, in the winding direction of their faces
+    #[allow(dead_code)]
+    pub fn boundary(&self) -> Vec<usize> {
+        (0..self.half_edges.len())
+            .filter(|&h| self.twins[h].is_none())
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_boundary_vertex(&self, vertex: u32) -> bool {
+        self.outgoing[vertex as usize]
+            .iter()
+            .any(|&h| self.twins[h].is_none() || self.twins[self.prev(h)].is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::Vec3;
+
+    /// Square split into four triangles around the centre vertex 4
+    fn fan() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.5, 0.5, 0.0),
+            ],
+            triangles: vec![(0, 1, 4), (1, 2, 4), (2, 3, 4), (3, 0, 4)],
+            lines: vec![],
+        }
+    }
+
+    #[test]
+    fn test_half_edges() {
+        let adjacency = Adjacency::new(&fan());
+        assert_eq!(adjacency.faces_count(), 4);
+        assert_eq!(adjacency.edges().len(), 8);
+        assert_eq!(adjacency.vertices(4), (2, 4));
+        assert_eq!(adjacency.next(5), 3);
+        assert_eq!(adjacency.prev(3), 5);
+        assert_eq!(adjacency.twin(4), Some(8));
+        assert_eq!(adjacency.twin(0), None);
+    }
+
+    #[test]
+    fn test_faces() {
+        let adjacency = Adjacency::new(&fan());
+        assert_eq!(adjacency.edge_faces(4, 1), vec![0, 1]);
+        assert_eq!(adjacency.edge_faces(0, 2), Vec::<usize>::new());
+        assert_eq!(adjacency.face_neighbours(0), vec![1, 3]);
+        assert_eq!(adjacency.vertex_faces(4).len(), 4);
+
+        let edge = adjacency.edge(1, 4).unwrap();
+        assert_eq!(adjacency.sides(edge), Some((0, 1)));
+        assert_eq!(adjacency.sides(adjacency.edge(0, 1).unwrap()), None);
+    }
+
+    #[test]
+    fn test_vertices() {
+        let adjacency = Adjacency::new(&fan());
+        assert_eq!(adjacency.vertex_neighbours(4), vec![0, 1, 2, 3]);
+        assert_eq!(adjacency.vertex_neighbours(0), vec![1, 3, 4]);
+        assert!(adjacency.is_boundary_vertex(0));
+        assert!(!adjacency.is_boundary_vertex(4));
+    }
+
+    #[test]
+    fn test_boundary() {
+        let adjacency = Adjacency::new(&fan());
+        let boundary: Vec<(u32, u32)> = adjacency
+            .boundary()
+            .iter()
+            .map(|&h| adjacency.vertices(h))
+            .collect();
+        assert_eq!(boundary, vec![(0, 1), (1, 2), (2, 3), (3, 0)]);
+    }
+
+    #[test]
+    fn test_inconsistent_winding_has_no_twins() {
+        let mut model = fan();
+        model.triangles[1] = (1, 4, 2);
+        let adjacency = Adjacency::new(&model);
+        assert_eq!(adjacency.twin(1), None);
+        assert_eq!(adjacency.edge_faces(1, 4), vec![0, 1]);
+    }
+}
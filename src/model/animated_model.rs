@@ -1,11 +1,41 @@
+use crate::model::baked::{BakeError, Baked};
+use crate::model::layers::{stacked, Layers};
 use crate::model::model::Model;
-use crate::model::Transform;
+use crate::model::timeline::{Step, Timeline};
+use crate::model::transform::fold::Fold;
+use crate::model::{Reversed, Symmetric, Symmetry, Transform, Unfold};
 use glissade::Easing;
+use std::cell::OnceCell;
+
+/// Part of the way to the stacking where faces that come together are not touching yet
+const APPROACH: f32 = 0.99;
+/// Even parts of a transformation checked for the faces stacking as they do at its end
+const STACKING_SAMPLES: usize = 16;
 
 pub trait AnimatedModel {
     fn duration(&self) -> f32;
     fn get_model(&self, time: f32) -> Model;
 
+    /// Folds of every animation step, in the order of steps
+    fn fold_steps(&self) -> Vec<Vec<Fold>> {
+        vec![]
+    }
+
+    /// Order of stacked faces during the step playing at `time`
+    fn layers(&self, _time: f32) -> Layers {
+        Layers::default()
+    }
+
+    /// Index in `fold_steps` of the step playing at `time`, none before the first step
+    fn step_at(&self, _time: f32) -> Option<usize> {
+        None
+    }
+
+    /// Labels and times of the steps in the order they play, see `Step::index`
+    fn timeline(&self) -> Timeline {
+        Timeline::default()
+    }
+
     fn animate<T>(self, duration: f32, transformation: T) -> ModelTransformation<Self, T>
     where
         T: Transform,
@@ -13,6 +43,39 @@ pub trait AnimatedModel {
     {
         ModelTransformation::new(self, transformation, duration)
     }
+
+    /// Play the animation backwards
+    #[allow(dead_code)]
+    fn reversed(self) -> Reversed<Self>
+    where
+        Self: Sized,
+    {
+        Reversed::new(self)
+    }
+
+    /// Sample the animation at `samples + 1` even times, for cheap playback and storage
+    fn bake(&self, samples: usize) -> Result<Baked, BakeError>
+    where
+        Self: Sized,
+    {
+        Baked::new(self, samples)
+    }
+
+    /// Show the animation with its symmetric copies, joined where they meet in the first state
+    fn symmetric(self, symmetry: Symmetry, tolerance: f32) -> Symmetric<Self>
+    where
+        Self: Sized,
+    {
+        Symmetric::new(self, symmetry, tolerance)
+    }
+
+    /// Unfold back to the first state after the animation, in `duration`
+    fn unfold(self, duration: f32) -> Unfold<Self>
+    where
+        Self: Sized,
+    {
+        Unfold::new(self, duration)
+    }
 }
 
 impl AnimatedModel for Model {
@@ -25,37 +88,271 @@ impl AnimatedModel for Model {
     }
 }
 
+impl AnimatedModel for Box<dyn AnimatedModel> {
+    fn duration(&self) -> f32 {
+        self.as_ref().duration()
+    }
+
+    fn get_model(&self, time: f32) -> Model {
+        self.as_ref().get_model(time)
+    }
+
+    fn fold_steps(&self) -> Vec<Vec<Fold>> {
+        self.as_ref().fold_steps()
+    }
+
+    fn layers(&self, time: f32) -> Layers {
+        self.as_ref().layers(time)
+    }
+
+    fn step_at(&self, time: f32) -> Option<usize> {
+        self.as_ref().step_at(time)
+    }
+
+    fn timeline(&self) -> Timeline {
+        self.as_ref().timeline()
+    }
+}
+
 pub struct ModelTransformation<M: AnimatedModel, T: Transform> {
     model: M,
     input: Model,
     transformation: T,
     duration: f32,
+    easing: Easing,
+    /// Time before the transformation starts
+    delay: f32,
+    /// Time the finished transformation stays before the next step
+    hold: f32,
+    label: Option<String>,
+    /// Layers at the end and the part of the transformation they start at,
+    /// found when they are first needed
+    layers: OnceCell<(Layers, f32)>,
 }
 
 impl<M: AnimatedModel, T: Transform> ModelTransformation<M, T> {
     pub fn new(model: M, transformation: T, duration: f32) -> Self {
+        let input = model.get_model(model.duration());
         Self {
-            input: model.get_model(model.duration()),
+            input,
             model,
             transformation,
             duration,
+            easing: Easing::QuadraticInOut,
+            delay: 0.0,
+            hold: 0.0,
+            label: None,
+            layers: OnceCell::new(),
+        }
+    }
+
+    /// Eased part of the transformation done at `time`
+    fn progress(&self, time: f32) -> f32 {
+        let elapsed = time - self.model.duration() - self.delay;
+        let t = if self.duration > 0.0 {
+            (elapsed / self.duration).clamp(0.0, 1.0)
+        } else if elapsed >= 0.0 {
+            1.0
+        } else {
+            0.0
+        };
+        self.easing.ease(t)
+    }
+
+    fn end_layers(&self) -> &(Layers, f32) {
+        self.layers.get_or_init(|| {
+            let after = self.transformation.apply(self.input.clone(), 1.0);
+            let stacks = stacked(&after);
+            let start = (0..STACKING_SAMPLES)
+                .map(|i| i as f32 / STACKING_SAMPLES as f32)
+                .find(|&t| {
+                    let model = self.transformation.apply(self.input.clone(), t);
+                    model.triangles == after.triangles && stacked(&model) == stacks
+                })
+                .unwrap_or(1.0);
+            let approach = self
+                .transformation
+                .apply(self.input.clone(), start * APPROACH);
+            let layers =
+                self.model
+                    .layers(self.model.duration())
+                    .next(&self.input, &approach, &after);
+            (layers, start)
+        })
+    }
+
+    pub fn easing(self, easing: Easing) -> Self {
+        Self { easing, ..self }
+    }
+
+    /// Custom easing curve, with the control points as in CSS `cubic-bezier`
+    #[allow(dead_code)]
+    pub fn bezier(self, x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        self.easing(Easing::bezier(x1, y1, x2, y2))
+    }
+
+    pub fn delay(self, delay: f32) -> Self {
+        Self { delay, ..self }
+    }
+
+    pub fn hold(self, hold: f32) -> Self {
+        Self { hold, ..self }
+    }
+
+    /// Name of the step, like a caption of the folding instructions
+    pub fn label(self, label: &str) -> Self {
+        Self {
+            label: Some(label.to_string()),
+            ..self
         }
     }
 }
 
 impl<M: AnimatedModel, T: Transform> AnimatedModel for ModelTransformation<M, T> {
     fn duration(&self) -> f32 {
-        self.model.duration() + self.duration
+        self.model.duration() + self.delay + self.duration + self.hold
     }
 
     fn get_model(&self, time: f32) -> Model {
         if time < self.model.duration() {
             self.model.get_model(time)
         } else {
-            let t = (time - self.model.duration()) / self.duration;
-            let t = t.clamp(0.0, 1.0);
-            let t = Easing::QuadraticInOut.ease(t);
-            self.transformation.apply(self.input.clone(), t)
+            self.transformation
+                .apply(self.input.clone(), self.progress(time))
+        }
+    }
+
+    fn fold_steps(&self) -> Vec<Vec<Fold>> {
+        let mut steps = self.model.fold_steps();
+        steps.push(self.transformation.folds(&self.input));
+        steps
+    }
+
+    /// The layers of the previous step are kept until the faces stack as they do
+    /// at the end, so stacks the transformation separates keep their order while they
+    /// still touch, and faces that come together early get their order when they touch.
+    /// The stacking is checked at `STACKING_SAMPLES` even parts of the transformation.
+    fn layers(&self, time: f32) -> Layers {
+        if time < self.model.duration() {
+            return self.model.layers(time);
+        }
+        let (layers, start) = self.end_layers();
+        if self.progress(time) < *start {
+            self.model.layers(self.model.duration())
+        } else {
+            layers.clone()
         }
     }
+
+    fn step_at(&self, time: f32) -> Option<usize> {
+        if time < self.model.duration() {
+            self.model.step_at(time)
+        } else {
+            Some(self.model.fold_steps().len())
+        }
+    }
+
+    fn timeline(&self) -> Timeline {
+        let mut timeline = self.model.timeline();
+        let begin = self.model.duration();
+        let start = begin + self.delay;
+        timeline.push(Step {
+            index: self.model.fold_steps().len(),
+            label: self.label.clone(),
+            begin,
+            motion: (start, start + self.duration),
+            end: self.duration(),
+        });
+        timeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Stay;
+    use glm::Vec3;
+
+    fn point() -> Model {
+        Model {
+            vertices: vec![Vec3::new(0.0, 0.0, 0.0)],
+            triangles: vec![],
+            lines: vec![],
+        }
+    }
+
+    fn x(model: &dyn AnimatedModel, time: f32) -> f32 {
+        model.get_model(time).vertices[0].x
+    }
+
+    #[test]
+    fn test_default_easing() {
+        let model = point().animate(2.0, Stay::new().shift_all(Vec3::x()));
+        assert_eq!(model.duration(), 2.0);
+        assert_eq!(x(&model, 0.5), 0.125);
+        assert_eq!(x(&model, 1.0), 0.5);
+        assert_eq!(x(&model, 2.0), 1.0);
+    }
+
+    #[test]
+    fn test_delay_and_hold() {
+        let model = point()
+            .animate(2.0, Stay::new().shift_all(Vec3::x()))
+            .easing(Easing::Linear)
+            .delay(1.0)
+            .hold(0.5);
+        assert_eq!(model.duration(), 3.5);
+        assert_eq!(x(&model, 0.5), 0.0);
+        assert_eq!(x(&model, 2.0), 0.5);
+        assert_eq!(x(&model, 3.0), 1.0);
+
+        let next = model.animate(1.0, Stay::new().shift_all(Vec3::x()));
+        assert_eq!(x(&next, 3.25), 1.0);
+        assert_eq!(x(&next, 4.5), 2.0);
+    }
+
+    #[test]
+    fn test_timeline() {
+        let model = point()
+            .animate(1.0, Stay::new().shift_all(Vec3::x()))
+            .label("right")
+            .animate(2.0, Stay::new().shift_all(Vec3::y()))
+            .delay(0.5)
+            .hold(1.0);
+        let timeline = model.timeline();
+        assert_eq!(timeline.steps().len(), model.fold_steps().len());
+        let step = &timeline.steps()[1];
+        assert_eq!(step.label, None);
+        assert_eq!((step.begin, step.motion, step.end), (1.0, (1.5, 3.5), 4.5));
+        assert_eq!(timeline.steps()[0].label.as_deref(), Some("right"));
+        for &time in [0.0, 0.5, 1.0, 2.0, 4.5].iter() {
+            let at = timeline.at(time).map(|at| at.index);
+            assert_eq!(at, model.step_at(time), "time {}", time);
+        }
+        assert_eq!(timeline.at(2.5).unwrap().progress, 0.5);
+    }
+
+    #[test]
+    fn test_bezier() {
+        let model = point()
+            .animate(1.0, Stay::new().shift_all(Vec3::x()))
+            .bezier(0.0, 1.0, 0.0, 1.0);
+        assert!(x(&model, 0.1) > 0.5);
+        assert!((x(&model, 1.0) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_snap() {
+        let model = point()
+            .animate(0.0, Stay::new().shift_all(Vec3::x()))
+            .delay(1.0)
+            .easing(Easing::None);
+        assert_eq!(x(&model, 0.5), 1.0);
+        let model = point()
+            .animate(0.0, Stay::new().shift_all(Vec3::x()))
+            .delay(1.0)
+            .easing(Easing::Linear);
+        assert_eq!(x(&model, 0.5), 0.0);
+        assert_eq!(x(&model, 1.0), 1.0);
+    }
 }
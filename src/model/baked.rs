@@ -0,0 +1,551 @@
+use crate::model::layers::Layers;
+use crate::model::timeline::{Step, Timeline};
+use crate::model::{AnimatedModel, Crease, Model};
+use glm::Vec3;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+const MAGIC: &[u8; 4] = b"BAKE";
+const VERSION: u32 = 2;
+/// Stored in place of a missing layer
+const NONE: i32 = i32::MIN;
+/// Stored in place of the length of a missing label
+const NO_LABEL: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BakeError {
+    /// Vertices or triangles of the model are different at `time` than at the start,
+    /// so the states can't be interpolated
+    TopologyChanges {
+        time: f32,
+    },
+    InvalidHeader,
+    UnsupportedVersion {
+        version: u32,
+    },
+    UnexpectedEnd,
+    UnknownCrease {
+        value: u8,
+    },
+    VertexOutOfRange {
+        vertex: u32,
+    },
+    /// Step label is not UTF-8 text
+    InvalidLabel {
+        step: usize,
+    },
+}
+
+impl Display for BakeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BakeError::TopologyChanges { time } => {
+                write!(f, "vertices or triangles change at time {}", time)
+            }
+            BakeError::InvalidHeader => write!(f, "not a baked animation"),
+            BakeError::UnsupportedVersion { version } => {
+                write!(f, "unsupported baked animation version {}", version)
+            }
+            BakeError::UnexpectedEnd => write!(f, "baked animation ends unexpectedly"),
+            BakeError::UnknownCrease { value } => write!(f, "unknown crease type {}", value),
+            BakeError::VertexOutOfRange { vertex } => {
+                write!(f, "vertex {} is out of the model", vertex)
+            }
+            BakeError::InvalidLabel { step } => write!(f, "label of step {} is not text", step),
+        }
+    }
+}
+
+impl Error for BakeError {}
+
+fn crease_code(crease: Crease) -> u8 {
+    match crease {
+        Crease::Mountain => 0,
+        Crease::Valley => 1,
+        Crease::Border => 2,
+        Crease::Cut => 3,
+        Crease::Guide => 4,
+    }
+}
+
+fn crease_from_code(value: u8) -> Result<Crease, BakeError> {
+    match value {
+        0 => Ok(Crease::Mountain),
+        1 => Ok(Crease::Valley),
+        2 => Ok(Crease::Border),
+        3 => Ok(Crease::Cut),
+        4 => Ok(Crease::Guide),
+        _ => Err(BakeError::UnknownCrease { value }),
+    }
+}
+
+/// State of the animation at one sample time
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    vertices: Vec<Vec3>,
+    /// Opacity of every line of the baked model, zero for lines missing in this state
+    opacities: Vec<f32>,
+    layers: Vec<Option<i32>>,
+}
+
+/// Animation sampled at even times, played back by interpolating the vertices
+/// of the nearest samples. The triangles must stay the same during the animation.
+/// Lines with no opacity are left out, and fold steps are not kept, only their timeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baked {
+    duration: f32,
+    triangles: Vec<(u32, u32, u32)>,
+    lines: Vec<(u32, u32, Crease)>,
+    timeline: Timeline,
+    frames: Vec<Frame>,
+}
+
+impl Baked {
+    /// Sample the `model` at `samples + 1` times from the start to the end
+    pub fn new(model: &dyn AnimatedModel, samples: usize) -> Result<Baked, BakeError> {
+        let duration = model.duration();
+        let first = model.get_model(0.0);
+        let mut lines: Vec<(u32, u32, Crease)> = Vec::new();
+        // Slots of the lines with the same vertices and crease, the same line can be repeated
+        let mut slots: HashMap<(u32, u32, Crease), Vec<usize>> = HashMap::new();
+        let mut frames: Vec<Frame> = Vec::with_capacity(samples + 1);
+
+        for i in 0..=samples {
+            let time = if samples > 0 {
+                duration * i as f32 / samples as f32
+            } else {
+                0.0
+            };
+            let state = model.get_model(time);
+            if state.vertices.len() != first.vertices.len() || state.triangles != first.triangles {
+                return Err(BakeError::TopologyChanges { time });
+            }
+
+            let mut opacities = vec![0.0; lines.len()];
+            let mut used: HashMap<(u32, u32, Crease), usize> = HashMap::new();
+            for &(a, b, opacity, crease) in state.lines.iter() {
+                let key = (a, b, crease);
+                let nth = used.entry(key).or_insert(0);
+                let same = slots.entry(key).or_default();
+                if *nth == same.len() {
+                    same.push(lines.len());
+                    lines.push(key);
+                    opacities.push(0.0);
+                }
+                opacities[same[*nth]] = opacity;
+                *nth += 1;
+            }
+
+            let layers = model.layers(time);
+            frames.push(Frame {
+                vertices: state.vertices,
+                opacities,
+                layers: (0..first.triangles.len())
+                    .map(|face| layers.get(face))
+                    .collect(),
+            });
+        }
+        for frame in frames.iter_mut() {
+            frame.opacities.resize(lines.len(), 0.0);
+        }
+
+        Ok(Baked {
+            duration,
+            triangles: first.triangles,
+            lines,
+            timeline: model.timeline(),
+            frames,
+        })
+    }
+
+    /// Index of the sample before `time` and the part of the way to the next one
+    fn position(&self, time: f32) -> (usize, f32) {
+        let last = self.frames.len() - 1;
+        if last == 0 || self.duration <= 0.0 {
+            return (0, 0.0);
+        }
+        let position = (time / self.duration).clamp(0.0, 1.0) * last as f32;
+        let index = (position.floor() as usize).min(last - 1);
+        (index, position - index as f32)
+    }
+
+    /// Index of the sample nearest to `time`
+    pub fn nearest_sample(&self, time: f32) -> usize {
+        let (index, t) = self.position(time);
+        if t < 0.5 {
+            index
+        } else {
+            index + 1
+        }
+    }
+
+    /// Vertices at `time`, interpolated between the nearest samples
+    pub fn vertices(&self, time: f32) -> Vec<Vec3> {
+        let (index, t) = self.position(time);
+        let from = &self.frames[index];
+        let to = self.frames.get(index + 1).unwrap_or(from);
+        from.vertices
+            .iter()
+            .zip(to.vertices.iter())
+            .map(|(a, b)| a.lerp(b, t))
+            .collect()
+    }
+
+    pub fn triangles(&self) -> &[(u32, u32, u32)] {
+        &self.triangles
+    }
+
+    fn nearest(&self, time: f32) -> &Frame {
+        &self.frames[self.nearest_sample(time)]
+    }
+
+    /// Compact little endian form, read it back with `from_bytes`
+    #[allow(dead_code)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(MAGIC);
+        bytes.extend(&VERSION.to_le_bytes());
+        bytes.extend(&self.duration.to_le_bytes());
+        let vertex_count = self.frames.first().map_or(0, |frame| frame.vertices.len());
+        for &count in [
+            self.frames.len(),
+            vertex_count,
+            self.triangles.len(),
+            self.lines.len(),
+            self.timeline.steps().len(),
+        ]
+        .iter()
+        {
+            bytes.extend(&(count as u32).to_le_bytes());
+        }
+
+        for &(a, b, c) in self.triangles.iter() {
+            for vertex in [a, b, c].iter() {
+                bytes.extend(&vertex.to_le_bytes());
+            }
+        }
+        for &(a, b, crease) in self.lines.iter() {
+            bytes.extend(&a.to_le_bytes());
+            bytes.extend(&b.to_le_bytes());
+            bytes.push(crease_code(crease));
+        }
+        for step in self.timeline.steps() {
+            bytes.extend(&(step.index as u32).to_le_bytes());
+            for time in [step.begin, step.motion.0, step.motion.1, step.end].iter() {
+                bytes.extend(&time.to_le_bytes());
+            }
+            match &step.label {
+                Some(label) => {
+                    bytes.extend(&(label.len() as u32).to_le_bytes());
+                    bytes.extend(label.as_bytes());
+                }
+                None => bytes.extend(&NO_LABEL.to_le_bytes()),
+            }
+        }
+        for frame in self.frames.iter() {
+            for vertex in frame.vertices.iter() {
+                for value in vertex.iter() {
+                    bytes.extend(&value.to_le_bytes());
+                }
+            }
+            for opacity in frame.opacities.iter() {
+                bytes.extend(&opacity.to_le_bytes());
+            }
+            for layer in frame.layers.iter() {
+                bytes.extend(&layer.unwrap_or(NONE).to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[allow(dead_code)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Baked, BakeError> {
+        let mut reader = Reader { bytes };
+        if reader
+            .take(MAGIC.len())
+            .map_err(|_| BakeError::InvalidHeader)?
+            != MAGIC
+        {
+            return Err(BakeError::InvalidHeader);
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(BakeError::UnsupportedVersion { version });
+        }
+        let duration = reader.f32()?;
+        let frame_count = reader.u32()? as usize;
+        let vertex_count = reader.u32()?;
+        let triangle_count = reader.u32()? as usize;
+        let line_count = reader.u32()? as usize;
+        let step_count = reader.u32()? as usize;
+        let vertex = |vertex: u32| {
+            if vertex < vertex_count {
+                Ok(vertex)
+            } else {
+                Err(BakeError::VertexOutOfRange { vertex })
+            }
+        };
+
+        // Counts are checked against the data before anything is allocated for them
+        reader.check(triangle_count, 12)?;
+        let triangles = (0..triangle_count)
+            .map(|_| {
+                Ok((
+                    vertex(reader.u32()?)?,
+                    vertex(reader.u32()?)?,
+                    vertex(reader.u32()?)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, BakeError>>()?;
+        reader.check(line_count, 9)?;
+        let lines = (0..line_count)
+            .map(|_| {
+                let (a, b) = (vertex(reader.u32()?)?, vertex(reader.u32()?)?);
+                Ok((a, b, crease_from_code(reader.take(1)?[0])?))
+            })
+            .collect::<Result<Vec<_>, BakeError>>()?;
+        reader.check(step_count, 24)?;
+        let mut timeline = Timeline::default();
+        for step in 0..step_count {
+            let index = reader.u32()? as usize;
+            let (begin, start, end, hold_end) =
+                (reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+            let label = match reader.u32()? {
+                NO_LABEL => None,
+                length => {
+                    let text = reader.take(length as usize)?.to_vec();
+                    Some(String::from_utf8(text).map_err(|_| BakeError::InvalidLabel { step })?)
+                }
+            };
+            timeline.push(Step {
+                index,
+                label,
+                begin,
+                motion: (start, end),
+                end: hold_end,
+            });
+        }
+
+        let frame_size = vertex_count as usize * 12 + line_count * 4 + triangle_count * 4;
+        reader.check(frame_count, frame_size)?;
+        let optional = |value: i32| if value == NONE { None } else { Some(value) };
+        let frames = (0..frame_count)
+            .map(|_| {
+                let vertices = (0..vertex_count)
+                    .map(|_| Ok(Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?)))
+                    .collect::<Result<Vec<_>, BakeError>>()?;
+                let opacities = (0..line_count)
+                    .map(|_| reader.f32())
+                    .collect::<Result<Vec<_>, BakeError>>()?;
+                let layers = (0..triangle_count)
+                    .map(|_| Ok(optional(reader.i32()?)))
+                    .collect::<Result<Vec<_>, BakeError>>()?;
+                Ok(Frame {
+                    vertices,
+                    opacities,
+                    layers,
+                })
+            })
+            .collect::<Result<Vec<_>, BakeError>>()?;
+        if frames.is_empty() {
+            return Err(BakeError::UnexpectedEnd);
+        }
+
+        Ok(Baked {
+            duration,
+            triangles,
+            lines,
+            timeline,
+            frames,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], BakeError> {
+        if self.bytes.len() < count {
+            return Err(BakeError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// There are at least `count` items of `size` bytes left
+    fn check(&self, count: usize, size: usize) -> Result<(), BakeError> {
+        match count.checked_mul(size) {
+            Some(total) if total <= self.bytes.len() => Ok(()),
+            _ => Err(BakeError::UnexpectedEnd),
+        }
+    }
+
+    fn word(&mut self) -> Result<[u8; 4], BakeError> {
+        Ok(self.take(4)?.try_into().expect("4 bytes are taken"))
+    }
+
+    fn u32(&mut self) -> Result<u32, BakeError> {
+        Ok(u32::from_le_bytes(self.word()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, BakeError> {
+        Ok(i32::from_le_bytes(self.word()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, BakeError> {
+        Ok(f32::from_le_bytes(self.word()?))
+    }
+}
+
+impl AnimatedModel for Baked {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn get_model(&self, time: f32) -> Model {
+        let (index, t) = self.position(time);
+        let from = &self.frames[index];
+        let to = self.frames.get(index + 1).unwrap_or(from);
+        let lines = self
+            .lines
+            .iter()
+            .zip(from.opacities.iter().zip(to.opacities.iter()))
+            .map(|(&(a, b, crease), (from, to))| (a, b, from * (1.0 - t) + to * t, crease))
+            .filter(|&(_, _, opacity, _)| opacity > 0.0)
+            .collect();
+        Model {
+            vertices: self.vertices(time),
+            triangles: self.triangles.clone(),
+            lines,
+        }
+    }
+
+    /// Layers of the nearest sample, they change at once during a step
+    fn layers(&self, time: f32) -> Layers {
+        self.nearest(time).layers.iter().copied().collect()
+    }
+
+    fn step_at(&self, time: f32) -> Option<usize> {
+        self.timeline.at(time).map(|at| at.index)
+    }
+
+    fn timeline(&self) -> Timeline {
+        self.timeline.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_set::BitSet;
+    use crate::model::transform::fold::Fold;
+    use crate::model::Transform;
+    use glissade::Easing;
+    use std::f32::consts::PI;
+
+    fn animated() -> impl AnimatedModel {
+        let model = Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(1.0, -1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 3, 1)],
+            lines: vec![(0, 2, 1.0, Crease::Border)],
+        };
+        model
+            .animate(1.0, Fold::new((0, 1), BitSet::with_bits(&[3]), PI))
+            .easing(Easing::Linear)
+            .label("Fold the corner under")
+            .animate(
+                1.0,
+                Fold::new((0, 1), BitSet::with_bits(&[2]), -PI)
+                    .add_lines(vec![(0, 1)], Crease::Valley),
+            )
+            .easing(Easing::Linear)
+    }
+
+    #[test]
+    fn test_samples_are_exact() {
+        let model = animated();
+        let baked = model.bake(8).unwrap();
+        assert_eq!(baked.duration(), 2.0);
+        assert_eq!(baked.timeline(), model.timeline());
+        for i in 0..=8 {
+            let time = i as f32 * 0.25;
+            let mut expected = model.get_model(time);
+            expected.lines.retain(|&(_, _, opacity, _)| opacity > 0.0);
+            let actual = baked.get_model(time);
+            assert_eq!(actual.vertices, expected.vertices, "time {}", time);
+            assert_eq!(actual.triangles, expected.triangles);
+            assert_eq!(actual.lines, expected.lines, "time {}", time);
+            assert_eq!(baked.step_at(time), model.step_at(time));
+            for face in 0..2 {
+                assert_eq!(baked.layers(time).get(face), model.layers(time).get(face));
+            }
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let baked = animated().bake(2).unwrap();
+        // Halfway between the first state and the corner folded up at 1.0
+        let model = baked.get_model(0.5);
+        assert!((model.vertices[3] - Vec3::new(1.0, 0.0, 0.0)).norm() < 1e-5);
+        assert_eq!(model.lines, vec![(0, 2, 1.0, Crease::Border)]);
+        let model = baked.get_model(1.5);
+        assert_eq!(model.lines[1], (0, 1, 0.5, Crease::Valley));
+        assert_eq!(baked.get_model(5.0).vertices, baked.get_model(2.0).vertices);
+    }
+
+    /// Model that gets a new triangle in the middle of the animation, like a cut
+    struct Growing;
+
+    impl AnimatedModel for Growing {
+        fn duration(&self) -> f32 {
+            1.0
+        }
+
+        fn get_model(&self, time: f32) -> Model {
+            let mut model = animated().get_model(0.0);
+            if time > 0.5 {
+                model.vertices.push(Vec3::new(2.0, 0.0, 0.0));
+                model.triangles.push((1, 4, 2));
+            }
+            model
+        }
+    }
+
+    #[test]
+    fn test_topology_changes() {
+        assert_eq!(
+            Growing.bake(4),
+            Err(BakeError::TopologyChanges { time: 0.75 })
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        let baked = animated().bake(8).unwrap();
+        let bytes = baked.to_bytes();
+        assert_eq!(Baked::from_bytes(&bytes), Ok(baked));
+
+        assert_eq!(
+            Baked::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BakeError::UnexpectedEnd)
+        );
+        assert_eq!(Baked::from_bytes(b"JSON"), Err(BakeError::InvalidHeader));
+        let mut future = bytes.clone();
+        future[4] = 3;
+        assert_eq!(
+            Baked::from_bytes(&future),
+            Err(BakeError::UnsupportedVersion { version: 3 })
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::model::adjacency::{Adjacency, Edge};
+use crate::model::{Crease, Model};
+use glm::Vec3;
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+/// Folds of this angle and sharper get fully opaque lines
+const FULL_ANGLE: f32 = PI * 0.5;
+/// Edges folded by less than this angle are flat and get no line
+const FLAT_ANGLE: f32 = 1e-3;
+/// Faces of a fold with the sine of the angle below this lie on each other,
+/// so the fold direction is not known from the angle
+const FOLDED_FLAT: f32 = 1e-3;
+
+fn normal(model: &Model, face: usize) -> Vec3 {
+    let (a, b, c) = model.triangles[face];
+    let v = |i: u32| model.vertices[i as usize];
+    (v(b) - v(a)).cross(&(v(c) - v(a))).normalize()
+}
+
+/// Fold angle of a manifold edge, positive for valley folds and negative for mountain ones,
+/// as in the FOLD format
+pub fn fold_angle(model: &Model, adjacency: &Adjacency, edge: &Edge) -> Option<f32> {
+    let (left, right) = adjacency.sides(edge)?;
+    let (n1, n2) = (normal(model, left), normal(model, right));
+    let (a, b) = edge.vertices;
+    let direction = (model.vertices[b as usize] - model.vertices[a as usize]).normalize();
+    Some((-n1.cross(&n2).dot(&direction)).atan2(n1.dot(&n2)))
+}
+
+impl Model {
+    /// Lines of the folded edges and the paper boundary, found from the mesh.
+    /// Lines get more opaque as the fold gets sharper, boundary lines are always opaque.
+    /// Faces folded flat onto each other have no fold direction, so it's taken
+    /// from the per-face `offsets` that move layers apart, see `Layers::offsets`.
+    pub fn creases(&self, offsets: &[Vec3]) -> Vec<(u32, u32, f32, Crease)> {
+        let adjacency = Adjacency::new(self);
+        let mut lines = Vec::new();
+        for edge in adjacency.edges() {
+            let (a, b) = edge.vertices;
+            let (left, right) = match adjacency.sides(edge) {
+                Some(sides) => sides,
+                None => {
+                    lines.push((a, b, 1.0, Crease::Border));
+                    continue;
+                }
+            };
+            let angle = match fold_angle(self, &adjacency, edge) {
+                Some(angle) if angle.abs() > FLAT_ANGLE => angle,
+                _ => continue,
+            };
+
+            let mut valley = angle > 0.0;
+            if angle.sin().abs() < FOLDED_FLAT {
+                if let (Some(l), Some(r)) = (offsets.get(left), offsets.get(right)) {
+                    // The right face is in front of the left one in a valley fold
+                    let side = (r - l).dot(&normal(self, left));
+                    if side != 0.0 {
+                        valley = side > 0.0;
+                    }
+                }
+            }
+            let crease = if valley {
+                Crease::Valley
+            } else {
+                Crease::Mountain
+            };
+            lines.push((a, b, (angle.abs() / FULL_ANGLE).min(1.0), crease));
+        }
+        lines
+    }
+
+    /// Lines found by `creases` with the lines of the model on the edges that have none,
+    /// like guides, cuts and creases that are not folded yet
+    pub fn lines_with_creases(&self, offsets: &[Vec3]) -> Vec<(u32, u32, f32, Crease)> {
+        let mut lines = self.creases(offsets);
+        let found: HashSet<(u32, u32)> = lines
+            .iter()
+            .map(|&(a, b, _, _)| (a.min(b), a.max(b)))
+            .collect();
+        lines.extend(
+            self.lines
+                .iter()
+                .copied()
+                .filter(|&(a, b, _, _)| !found.contains(&(a.min(b), a.max(b)))),
+        );
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_set::BitSet;
+    use crate::model::transform::fold::Fold;
+    use crate::model::Transform;
+
+    fn square() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3)],
+            lines: vec![],
+        }
+    }
+
+    /// Fold of the corner 3 over the diagonal
+    fn fold(angle: f32) -> Fold {
+        Fold::new((0, 2), BitSet::with_bits(&[3]), angle)
+    }
+
+    fn diagonal(model: &Model, offsets: &[Vec3]) -> Option<(f32, Crease)> {
+        model
+            .creases(offsets)
+            .into_iter()
+            .find(|&(a, b, _, _)| (a, b) == (0, 2))
+            .map(|(_, _, opacity, crease)| (opacity, crease))
+    }
+
+    #[test]
+    fn test_flat_sheet_has_border() {
+        let lines = square().creases(&[]);
+        assert_eq!(lines.len(), 4);
+        assert!(lines
+            .iter()
+            .all(|&(_, _, opacity, crease)| opacity == 1.0 && crease == Crease::Border));
+    }
+
+    #[test]
+    fn test_fold_direction_and_strength() {
+        // The corner goes up to the front side of the sheet
+        let model = fold(PI * 0.25).apply(square(), 1.0);
+        assert!(model.vertices[3].z > 0.0);
+        let (opacity, crease) = diagonal(&model, &[]).unwrap();
+        assert_eq!(crease, Crease::Valley);
+        assert!((opacity - 0.5).abs() < 1e-5);
+
+        let model = fold(-PI * 0.75).apply(square(), 1.0);
+        assert_eq!(diagonal(&model, &[]), Some((1.0, Crease::Mountain)));
+    }
+
+    #[test]
+    fn test_folded_flat() {
+        let model = fold(PI).apply(square(), 1.0);
+        let below = [Vec3::zeros(), Vec3::new(0.0, 0.0, -0.1)];
+        assert_eq!(diagonal(&model, &below), Some((1.0, Crease::Mountain)));
+        let above = [Vec3::zeros(), Vec3::new(0.0, 0.0, 0.1)];
+        assert_eq!(diagonal(&model, &above), Some((1.0, Crease::Valley)));
+    }
+
+    #[test]
+    fn test_lines_with_creases() {
+        let mut model = square();
+        model.lines = vec![(2, 0, 0.5, Crease::Guide), (0, 1, 1.0, Crease::Border)];
+        let lines = model.lines_with_creases(&[]);
+        assert_eq!(lines.len(), 5);
+        assert!(lines.contains(&(2, 0, 0.5, Crease::Guide)));
+
+        let folded = fold(-PI * 0.75).apply(model, 1.0);
+        let lines = folded.lines_with_creases(&[]);
+        assert_eq!(lines.len(), 5);
+        assert!(lines.contains(&(0, 2, 1.0, Crease::Mountain)));
+    }
+}
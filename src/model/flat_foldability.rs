@@ -0,0 +1,264 @@
+use crate::model::adjacency::Adjacency;
+use crate::model::{Crease, Model};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Local conditions for the creases around an interior vertex to fold flat
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// Counts of mountain and valley creases don't differ by two, Maekawa's theorem
+    Maekawa {
+        vertex: u32,
+        mountains: usize,
+        valleys: usize,
+    },
+    /// Alternating sum of the angles between the creases is not zero, Kawasaki's theorem.
+    /// `error` is the absolute value of the sum, in radians.
+    Kawasaki { vertex: u32, error: f32 },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Maekawa {
+                vertex,
+                mountains,
+                valleys,
+            } => write!(
+                f,
+                "vertex {} has {} mountain and {} valley creases, which don't differ by 2",
+                vertex, mountains, valleys
+            ),
+            Violation::Kawasaki { vertex, error } => write!(
+                f,
+                "alternating sum of angles at vertex {} is off by {} degrees",
+                vertex,
+                error.to_degrees()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FoldabilityReport {
+    pub violations: Vec<Violation>,
+}
+
+impl FoldabilityReport {
+    #[allow(dead_code)]
+    pub fn is_flat_foldable(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl Display for FoldabilityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.violations.is_empty() {
+            return write!(f, "crease pattern is flat-foldable");
+        }
+        for violation in self.violations.iter() {
+            writeln!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Angle of the face corner at the start of the half-edge
+fn corner_angle(model: &Model, adjacency: &Adjacency, half_edge: usize) -> f32 {
+    let (vertex, next) = adjacency.vertices(half_edge);
+    let (previous, _) = adjacency.vertices(adjacency.prev(half_edge));
+    let v = |i: u32| model.vertices[i as usize];
+    glm::angle(&(v(next) - v(vertex)), &(v(previous) - v(vertex)))
+}
+
+/// Edges from an interior vertex to its neighbours with the face corner angles after them,
+/// in the winding order, none for boundary vertices
+fn fan(model: &Model, adjacency: &Adjacency, vertex: u32) -> Option<Vec<(u32, f32)>> {
+    let outgoing = adjacency.vertex_half_edges(vertex);
+    let first = *outgoing.first()?;
+    let mut fan = Vec::with_capacity(outgoing.len());
+    let mut half_edge = first;
+    loop {
+        fan.push((
+            adjacency.vertices(half_edge).1,
+            corner_angle(model, adjacency, half_edge),
+        ));
+        half_edge = adjacency.twin(adjacency.prev(half_edge))?;
+        if half_edge == first {
+            return Some(fan);
+        }
+        if fan.len() >= outgoing.len() {
+            return None;
+        }
+    }
+}
+
+impl Model {
+    /// Check that the mountain and valley lines can fold flat around every interior vertex,
+    /// with Maekawa's and Kawasaki's theorems. The angles are measured on the faces,
+    /// so the model doesn't have to be unfolded. Kawasaki's theorem fails when the
+    /// alternating sum is off by more than `tolerance` radians. Vertices with an odd count
+    /// of creases fail Maekawa's theorem and have no alternating sum.
+    #[allow(dead_code)]
+    pub fn check_flat_foldable(&self, tolerance: f32) -> FoldabilityReport {
+        let adjacency = Adjacency::new(self);
+        let creases: HashMap<(u32, u32), Crease> = self
+            .lines
+            .iter()
+            .filter(|&&(_, _, _, crease)| crease == Crease::Mountain || crease == Crease::Valley)
+            .map(|&(a, b, _, crease)| ((a.min(b), a.max(b)), crease))
+            .collect();
+
+        let mut violations = Vec::new();
+        for vertex in 0..self.vertices.len() as u32 {
+            let fan = match fan(self, &adjacency, vertex) {
+                Some(fan) => fan,
+                None => continue,
+            };
+            let crease_at = |other: u32| creases.get(&(vertex.min(other), vertex.max(other)));
+            // Start after a crease, so every sector is between two creases
+            let start = match fan
+                .iter()
+                .position(|&(other, _)| crease_at(other).is_some())
+            {
+                Some(start) => start,
+                None => continue,
+            };
+
+            let (mut mountains, mut valleys) = (0, 0);
+            let mut sectors = Vec::new();
+            let mut sector = 0.0;
+            for &(other, angle) in fan[start..].iter().chain(fan[..start].iter()) {
+                match crease_at(other) {
+                    Some(Crease::Mountain) => mountains += 1,
+                    Some(_) => valleys += 1,
+                    None => {
+                        sector += angle;
+                        continue;
+                    }
+                }
+                if mountains + valleys > 1 {
+                    sectors.push(sector);
+                }
+                sector = angle;
+            }
+            sectors.push(sector);
+
+            if (mountains as i64 - valleys as i64).abs() != 2 {
+                violations.push(Violation::Maekawa {
+                    vertex,
+                    mountains,
+                    valleys,
+                });
+            }
+            if sectors.len() % 2 == 0 {
+                let error: f32 = sectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &angle)| if i % 2 == 0 { angle } else { -angle })
+                    .sum::<f32>()
+                    .abs();
+                if error > tolerance {
+                    violations.push(Violation::Kawasaki { vertex, error });
+                }
+            }
+        }
+        FoldabilityReport { violations }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::Vec3;
+    use std::f32::consts::PI;
+    use Crease::{Mountain, Valley};
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn direction(degrees: f32) -> Vec3 {
+        let angle = degrees.to_radians();
+        Vec3::new(angle.cos(), angle.sin(), 0.0)
+    }
+
+    /// Degree-4 vertex with sector angles of 45, 90, 135 and 90 degrees,
+    /// with the triangles split by a guide line in the 135 degrees sector
+    fn bird_foot(creases: [Crease; 4]) -> Model {
+        let vertices = vec![
+            Vec3::zeros(),
+            direction(0.0),
+            direction(45.0),
+            direction(135.0),
+            direction(200.0),
+            direction(270.0),
+        ];
+        let mut lines: Vec<(u32, u32, f32, Crease)> = [1, 2, 3, 5]
+            .iter()
+            .zip(creases.iter())
+            .map(|(&v, &crease)| (0, v, 1.0, crease))
+            .collect();
+        lines.push((0, 4, 1.0, Crease::Guide));
+        lines.push((1, 2, 1.0, Crease::Border));
+        Model {
+            vertices,
+            triangles: vec![(0, 1, 2), (0, 2, 3), (0, 3, 4), (0, 4, 5), (0, 5, 1)],
+            lines,
+        }
+    }
+
+    #[test]
+    fn test_flat_foldable() {
+        let report = bird_foot([Valley, Mountain, Valley, Valley]).check_flat_foldable(TOLERANCE);
+        assert!(report.is_flat_foldable(), "{}", report);
+        assert_eq!(report.to_string(), "crease pattern is flat-foldable");
+    }
+
+    #[test]
+    fn test_maekawa() {
+        let report = bird_foot([Valley, Mountain, Mountain, Valley]).check_flat_foldable(TOLERANCE);
+        assert_eq!(
+            report.violations,
+            vec![Violation::Maekawa {
+                vertex: 0,
+                mountains: 2,
+                valleys: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_kawasaki() {
+        let mut model = bird_foot([Valley, Mountain, Valley, Valley]);
+        // Sectors of 60, 75, 135 and 90 degrees
+        model.vertices[2] = direction(60.0);
+        let report = model.check_flat_foldable(TOLERANCE);
+        match report.violations[..] {
+            [Violation::Kawasaki { vertex: 0, error }] => assert!((error - PI / 6.0).abs() < 1e-4),
+            _ => panic!("{:?}", report),
+        }
+        assert!(model.check_flat_foldable(PI / 5.0).is_flat_foldable());
+    }
+
+    #[test]
+    fn test_odd_degree() {
+        let mut model = bird_foot([Valley, Mountain, Valley, Valley]);
+        model.lines.remove(0);
+        let report = model.check_flat_foldable(TOLERANCE);
+        assert_eq!(
+            report.violations,
+            vec![Violation::Maekawa {
+                vertex: 0,
+                mountains: 1,
+                valleys: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_any_plane() {
+        // Angles are measured on the faces, so the pattern doesn't have to be in z = 0
+        let rotation = glm::rotation(1.0, &Vec3::new(1.0, 2.0, 3.0));
+        let model = bird_foot([Valley, Mountain, Valley, Valley]).transform(rotation);
+        assert!(model.check_flat_foldable(TOLERANCE).is_flat_foldable());
+    }
+}
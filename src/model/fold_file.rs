@@ -0,0 +1,652 @@
+use crate::model::adjacency::Adjacency;
+use crate::model::creases::fold_angle;
+use crate::model::{AnimatedModel, Crease, Model};
+use glm::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Crease assignment of an edge, as `edges_assignment` of the FOLD format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeAssignment {
+    Mountain,
+    Valley,
+    Border,
+    Flat,
+    Cut,
+    Unassigned,
+}
+
+impl EdgeAssignment {
+    pub fn parse(code: &str) -> Option<EdgeAssignment> {
+        match code {
+            "M" | "m" => Some(EdgeAssignment::Mountain),
+            "V" | "v" => Some(EdgeAssignment::Valley),
+            "B" | "b" => Some(EdgeAssignment::Border),
+            "F" | "f" => Some(EdgeAssignment::Flat),
+            "C" | "c" => Some(EdgeAssignment::Cut),
+            "U" | "u" => Some(EdgeAssignment::Unassigned),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            EdgeAssignment::Mountain => "M",
+            EdgeAssignment::Valley => "V",
+            EdgeAssignment::Border => "B",
+            EdgeAssignment::Flat => "F",
+            EdgeAssignment::Cut => "C",
+            EdgeAssignment::Unassigned => "U",
+        }
+    }
+
+    /// Type of the line drawn for the edge, flat and unassigned edges are guides
+    pub fn crease(self) -> Crease {
+        match self {
+            EdgeAssignment::Mountain => Crease::Mountain,
+            EdgeAssignment::Valley => Crease::Valley,
+            EdgeAssignment::Border => Crease::Border,
+            EdgeAssignment::Cut => Crease::Cut,
+            EdgeAssignment::Flat | EdgeAssignment::Unassigned => Crease::Guide,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FoldFileError {
+    Json(serde_json::Error),
+    FrameNotFound {
+        frame: usize,
+    },
+    /// The `frame_parent` chain of the frame comes back to a frame it has passed
+    ParentCycle {
+        frame: usize,
+    },
+    MissingField {
+        frame: usize,
+        field: &'static str,
+    },
+    InvalidCoordinates {
+        vertex: usize,
+        dimensions: usize,
+    },
+    VertexOutOfRange {
+        field: &'static str,
+        item: usize,
+        vertex: u32,
+    },
+    DegenerateFace {
+        face: usize,
+        vertices: usize,
+    },
+    AssignmentCountMismatch {
+        edges: usize,
+        assignments: usize,
+    },
+    UnknownAssignment {
+        edge: usize,
+        assignment: String,
+    },
+    VertexCountMismatch {
+        crease_pattern: usize,
+        folded: usize,
+    },
+}
+
+impl Display for FoldFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FoldFileError::Json(error) => write!(f, "invalid FOLD json: {}", error),
+            FoldFileError::FrameNotFound { frame } => write!(f, "frame {} not found", frame),
+            FoldFileError::ParentCycle { frame } => {
+                write!(f, "frame_parent of frame {} makes a cycle", frame)
+            }
+            FoldFileError::MissingField { frame, field } => {
+                write!(f, "frame {} has no `{}` field", frame, field)
+            }
+            FoldFileError::InvalidCoordinates { vertex, dimensions } => write!(
+                f,
+                "vertices_coords[{}] has {} coordinates, expected 2 or 3",
+                vertex, dimensions
+            ),
+            FoldFileError::VertexOutOfRange {
+                field,
+                item,
+                vertex,
+            } => write!(f, "{}[{}] refers to missing vertex {}", field, item, vertex),
+            FoldFileError::DegenerateFace { face, vertices } => write!(
+                f,
+                "faces_vertices[{}] has {} vertices, expected at least 3",
+                face, vertices
+            ),
+            FoldFileError::AssignmentCountMismatch { edges, assignments } => write!(
+                f,
+                "edges_assignment has {} entries for {} edges",
+                assignments, edges
+            ),
+            FoldFileError::UnknownAssignment { edge, assignment } => write!(
+                f,
+                "edges_assignment[{}] is {:?}, expected one of M, V, B, F, C, U",
+                edge, assignment
+            ),
+            FoldFileError::VertexCountMismatch {
+                crease_pattern,
+                folded,
+            } => write!(
+                f,
+                "crease pattern has {} vertices, but folded state has {}",
+                crease_pattern, folded
+            ),
+        }
+    }
+}
+
+impl Error for FoldFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FoldFileError::Json(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for FoldFileError {
+    fn from(error: serde_json::Error) -> Self {
+        FoldFileError::Json(error)
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+struct Frame {
+    vertices_coords: Option<Vec<Vec<f32>>>,
+    faces_vertices: Option<Vec<Vec<u32>>>,
+    edges_vertices: Option<Vec<[u32; 2]>>,
+    edges_assignment: Option<Vec<String>>,
+    frame_parent: Option<usize>,
+    #[serde(default)]
+    frame_inherit: bool,
+}
+
+impl Frame {
+    fn inherit(self, parent: Frame) -> Frame {
+        Frame {
+            vertices_coords: self.vertices_coords.or(parent.vertices_coords),
+            faces_vertices: self.faces_vertices.or(parent.faces_vertices),
+            edges_vertices: self.edges_vertices.or(parent.edges_vertices),
+            edges_assignment: self.edges_assignment.or(parent.edges_assignment),
+            ..self
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FoldFile {
+    #[serde(flatten)]
+    key_frame: Frame,
+    #[serde(default)]
+    file_frames: Vec<Frame>,
+}
+
+impl FoldFile {
+    /// Frame 0 is the key frame, frame `i` is `file_frames[i - 1]`
+    fn frame(&self, frame: usize) -> Result<Frame, FoldFileError> {
+        let mut result = self.get(frame)?;
+        let mut visited = vec![frame];
+        let mut current = frame;
+        while current != 0 && result.frame_inherit {
+            current = result.frame_parent.unwrap_or(0);
+            if visited.contains(&current) {
+                return Err(FoldFileError::ParentCycle { frame });
+            }
+            visited.push(current);
+            let parent = self.get(current)?;
+            let inherit = parent.frame_inherit;
+            result = Frame {
+                frame_parent: parent.frame_parent,
+                frame_inherit: inherit,
+                ..result.inherit(parent)
+            };
+        }
+        Ok(result)
+    }
+
+    fn get(&self, frame: usize) -> Result<Frame, FoldFileError> {
+        if frame == 0 {
+            Ok(self.key_frame.clone())
+        } else {
+            self.file_frames
+                .get(frame - 1)
+                .cloned()
+                .ok_or(FoldFileError::FrameNotFound { frame })
+        }
+    }
+}
+
+/// Model loaded from a FOLD file, `assignments[i]` is the crease type of `model.lines[i]`.
+/// Flat edges are not crease lines, so they are skipped.
+#[derive(Debug, Clone)]
+pub struct FoldPattern {
+    pub model: Model,
+    pub assignments: Vec<EdgeAssignment>,
+}
+
+#[allow(dead_code)]
+impl FoldPattern {
+    /// Load the key frame of a FOLD file
+    pub fn from_json(json: &str) -> Result<FoldPattern, FoldFileError> {
+        FoldPattern::from_json_frame(json, 0)
+    }
+
+    /// Load a frame of a FOLD file, e.g. a folded state stored in `file_frames`
+    pub fn from_json_frame(json: &str, frame: usize) -> Result<FoldPattern, FoldFileError> {
+        let file: FoldFile = serde_json::from_str(json)?;
+        FoldPattern::from_frame(file.frame(frame)?, frame)
+    }
+
+    fn from_frame(frame: Frame, index: usize) -> Result<FoldPattern, FoldFileError> {
+        let coords = frame.vertices_coords.ok_or(FoldFileError::MissingField {
+            frame: index,
+            field: "vertices_coords",
+        })?;
+        let faces = frame.faces_vertices.ok_or(FoldFileError::MissingField {
+            frame: index,
+            field: "faces_vertices",
+        })?;
+        let edges = frame.edges_vertices.unwrap_or_default();
+
+        let vertices = coords
+            .iter()
+            .enumerate()
+            .map(|(i, c)| match c.as_slice() {
+                [x, y] => Ok(Vec3::new(*x, *y, 0.0)),
+                [x, y, z] => Ok(Vec3::new(*x, *y, *z)),
+                _ => Err(FoldFileError::InvalidCoordinates {
+                    vertex: i,
+                    dimensions: c.len(),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let check = |field: &'static str, item: usize, vertex: u32| {
+            if (vertex as usize) < vertices.len() {
+                Ok(vertex)
+            } else {
+                Err(FoldFileError::VertexOutOfRange {
+                    field,
+                    item,
+                    vertex,
+                })
+            }
+        };
+
+        // Faces are expected to be convex, so a fan covers them
+        let mut triangles = Vec::new();
+        for (i, face) in faces.iter().enumerate() {
+            if face.len() < 3 {
+                return Err(FoldFileError::DegenerateFace {
+                    face: i,
+                    vertices: face.len(),
+                });
+            }
+            let first = check("faces_vertices", i, face[0])?;
+            for pair in face[1..].windows(2) {
+                triangles.push((
+                    first,
+                    check("faces_vertices", i, pair[0])?,
+                    check("faces_vertices", i, pair[1])?,
+                ));
+            }
+        }
+
+        let assignments = match frame.edges_assignment {
+            Some(codes) => {
+                if codes.len() != edges.len() {
+                    return Err(FoldFileError::AssignmentCountMismatch {
+                        edges: edges.len(),
+                        assignments: codes.len(),
+                    });
+                }
+                codes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, code)| {
+                        EdgeAssignment::parse(code).ok_or_else(|| {
+                            FoldFileError::UnknownAssignment {
+                                edge: i,
+                                assignment: code.clone(),
+                            }
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            None => vec![EdgeAssignment::Unassigned; edges.len()],
+        };
+
+        let mut lines = Vec::new();
+        let mut line_assignments = Vec::new();
+        for (i, (&[a, b], &assignment)) in edges.iter().zip(assignments.iter()).enumerate() {
+            let a = check("edges_vertices", i, a)?;
+            let b = check("edges_vertices", i, b)?;
+            if assignment != EdgeAssignment::Flat {
+                lines.push((a, b, 1.0, assignment.crease()));
+                line_assignments.push(assignment);
+            }
+        }
+
+        Ok(FoldPattern {
+            model: Model {
+                vertices,
+                triangles,
+                lines,
+            },
+            assignments: line_assignments,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ExportFile {
+    file_spec: f32,
+    file_creator: &'static str,
+    file_classes: Vec<&'static str>,
+    frame_classes: Vec<&'static str>,
+    vertices_coords: Vec<Vec<f32>>,
+    edges_vertices: Vec<[u32; 2]>,
+    edges_assignment: Vec<&'static str>,
+    faces_vertices: Vec<[u32; 3]>,
+    file_frames: Vec<ExportFrame>,
+}
+
+#[derive(Serialize)]
+struct ExportFrame {
+    frame_classes: Vec<&'static str>,
+    frame_parent: usize,
+    frame_inherit: bool,
+    vertices_coords: Vec<Vec<f32>>,
+    #[serde(rename = "edges_foldAngle")]
+    edges_fold_angle: Vec<f32>,
+}
+
+/// Fold angles smaller than this, in degrees, are treated as flat
+const FLAT_ANGLE: f32 = 0.01;
+
+/// Write a FOLD file with `crease_pattern` as the key frame and `folded` as a folded form frame.
+/// Both models must have the same vertices, `folded` provides triangles and crease lines.
+/// Crease lines that are folded get a mountain or valley assignment from the folded state,
+/// flat ones keep the crease type of the line.
+#[allow(dead_code)]
+pub fn export(crease_pattern: &Model, folded: &Model) -> Result<String, FoldFileError> {
+    if crease_pattern.vertices.len() != folded.vertices.len() {
+        return Err(FoldFileError::VertexCountMismatch {
+            crease_pattern: crease_pattern.vertices.len(),
+            folded: folded.vertices.len(),
+        });
+    }
+
+    let adjacency = Adjacency::new(folded);
+    let edges: Vec<(u32, u32)> = adjacency.edges().iter().map(|edge| edge.vertices).collect();
+    let sides: Vec<Option<(usize, usize)>> = adjacency
+        .edges()
+        .iter()
+        .map(|edge| adjacency.sides(edge))
+        .collect();
+
+    let fold_angles: Vec<f32> = adjacency
+        .edges()
+        .iter()
+        .map(|edge| fold_angle(folded, &adjacency, edge).map_or(0.0, f32::to_degrees))
+        .collect();
+
+    let creases: HashMap<(u32, u32), Crease> = folded
+        .lines
+        .iter()
+        .map(|&(a, b, _, crease)| ((a.min(b), a.max(b)), crease))
+        .collect();
+    let assignments = edges
+        .iter()
+        .zip(sides.iter().zip(fold_angles.iter()))
+        .map(|(edge, (side, &angle))| {
+            let assignment = match (side, creases.get(edge)) {
+                (Some(_), Some(&crease)) => {
+                    let valley =
+                        angle > FLAT_ANGLE || angle.abs() <= FLAT_ANGLE && crease == Crease::Valley;
+                    let mountain = angle < -FLAT_ANGLE
+                        || angle.abs() <= FLAT_ANGLE && crease == Crease::Mountain;
+                    if valley {
+                        EdgeAssignment::Valley
+                    } else if mountain {
+                        EdgeAssignment::Mountain
+                    } else {
+                        EdgeAssignment::Unassigned
+                    }
+                }
+                (Some(_), None) => EdgeAssignment::Flat,
+                (None, _) => EdgeAssignment::Border,
+            };
+            assignment.code()
+        })
+        .collect();
+
+    let coords = |model: &Model, flat: bool| {
+        model
+            .vertices
+            .iter()
+            .map(|v| {
+                if flat {
+                    vec![v.x, v.y]
+                } else {
+                    vec![v.x, v.y, v.z]
+                }
+            })
+            .collect()
+    };
+    let is_flat = crease_pattern.vertices.iter().all(|v| v.z == 0.0);
+
+    let file = ExportFile {
+        file_spec: 1.1,
+        file_creator: "paper-plane",
+        file_classes: vec!["singleModel"],
+        frame_classes: vec!["creasePattern"],
+        vertices_coords: coords(crease_pattern, is_flat),
+        edges_vertices: edges.iter().map(|&(a, b)| [a, b]).collect(),
+        edges_assignment: assignments,
+        faces_vertices: folded
+            .triangles
+            .iter()
+            .map(|&(a, b, c)| [a, b, c])
+            .collect(),
+        file_frames: vec![ExportFrame {
+            frame_classes: vec!["foldedForm"],
+            frame_parent: 0,
+            frame_inherit: true,
+            vertices_coords: coords(folded, false),
+            edges_fold_angle: fold_angles,
+        }],
+    };
+
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+/// Write a FOLD file with the rest state of `model` as the crease pattern
+/// and its state at `time` as the folded form
+#[allow(dead_code)]
+pub fn export_animated(model: &dyn AnimatedModel, time: f32) -> Result<String, FoldFileError> {
+    export(&model.get_model(0.0), &model.get_model(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_set::BitSet;
+    use crate::model::transform::fold::Fold;
+    use std::f32::consts::PI;
+
+    const SQUARE: &str = r#"{
+        "file_spec": 1.1,
+        "vertices_coords": [[0, 0], [1, 0], [1, 1], [0, 1]],
+        "faces_vertices": [[0, 1, 2], [0, 2, 3]],
+        "edges_vertices": [[0, 1], [1, 2], [2, 3], [3, 0], [0, 2]],
+        "edges_assignment": ["B", "B", "B", "B", "V"],
+        "file_frames": [{
+            "frame_classes": ["foldedForm"],
+            "frame_inherit": true,
+            "frame_parent": 0,
+            "vertices_coords": [[0, 0, 0], [1, 0, 0], [1, 1, 0], [0, 0, 1]]
+        }]
+    }"#;
+
+    #[test]
+    fn test_load_crease_pattern() {
+        let pattern = FoldPattern::from_json(SQUARE).unwrap();
+        assert_eq!(pattern.model.vertices.len(), 4);
+        assert_eq!(pattern.model.vertices[2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(pattern.model.triangles, vec![(0, 1, 2), (0, 2, 3)]);
+        assert_eq!(pattern.model.lines.len(), 5);
+        assert_eq!(pattern.model.lines[4], (0, 2, 1.0, Crease::Valley));
+        assert_eq!(pattern.assignments[0], EdgeAssignment::Border);
+        assert_eq!(pattern.assignments[4], EdgeAssignment::Valley);
+    }
+
+    #[test]
+    fn test_load_folded_frame() {
+        let pattern = FoldPattern::from_json_frame(SQUARE, 1).unwrap();
+        assert_eq!(pattern.model.vertices[3], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(pattern.model.triangles.len(), 2);
+        assert_eq!(pattern.assignments.len(), 5);
+    }
+
+    #[test]
+    fn test_polygon_faces_and_flat_edges() {
+        let pattern = FoldPattern::from_json(
+            r#"{
+                "vertices_coords": [[0, 0], [1, 0], [1, 1], [0, 1]],
+                "faces_vertices": [[0, 1, 2, 3]],
+                "edges_vertices": [[0, 1], [0, 2]],
+                "edges_assignment": ["M", "F"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(pattern.model.triangles, vec![(0, 1, 2), (0, 2, 3)]);
+        assert_eq!(pattern.model.lines, vec![(0, 1, 1.0, Crease::Mountain)]);
+        assert_eq!(pattern.assignments, vec![EdgeAssignment::Mountain]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |json: &str| FoldPattern::from_json(json).unwrap_err().to_string();
+
+        assert!(error("{").starts_with("invalid FOLD json"));
+        assert_eq!(
+            error(r#"{"faces_vertices": []}"#),
+            "frame 0 has no `vertices_coords` field"
+        );
+        assert_eq!(
+            error(r#"{"vertices_coords": [[0]], "faces_vertices": []}"#),
+            "vertices_coords[0] has 1 coordinates, expected 2 or 3"
+        );
+        assert_eq!(
+            error(r#"{"vertices_coords": [[0, 0]], "faces_vertices": [[0, 0, 3]]}"#),
+            "faces_vertices[0] refers to missing vertex 3"
+        );
+        assert_eq!(
+            error(r#"{"vertices_coords": [[0, 0]], "faces_vertices": [[0, 0]]}"#),
+            "faces_vertices[0] has 2 vertices, expected at least 3"
+        );
+        assert_eq!(
+            error(
+                r#"{"vertices_coords": [[0, 0]], "faces_vertices": [],
+                    "edges_vertices": [[0, 0]], "edges_assignment": ["X"]}"#
+            ),
+            "edges_assignment[0] is \"X\", expected one of M, V, B, F, C, U"
+        );
+        assert_eq!(
+            error(
+                r#"{"vertices_coords": [[0, 0]], "faces_vertices": [],
+                    "edges_vertices": [[0, 0]], "edges_assignment": []}"#
+            ),
+            "edges_assignment has 0 entries for 1 edges"
+        );
+        assert_eq!(
+            FoldPattern::from_json_frame(SQUARE, 2)
+                .unwrap_err()
+                .to_string(),
+            "frame 2 not found"
+        );
+        let cycle = r#"{
+            "vertices_coords": [[0, 0]], "faces_vertices": [],
+            "file_frames": [
+                {"frame_parent": 2, "frame_inherit": true},
+                {"frame_parent": 1, "frame_inherit": true}
+            ]
+        }"#;
+        assert_eq!(
+            FoldPattern::from_json_frame(cycle, 1)
+                .unwrap_err()
+                .to_string(),
+            "frame_parent of frame 1 makes a cycle"
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let crease_pattern = FoldPattern::from_json(SQUARE).unwrap().model;
+        let mut folded = FoldPattern::from_json_frame(SQUARE, 1).unwrap().model;
+        let json = export(&crease_pattern, &folded).unwrap();
+
+        let pattern = FoldPattern::from_json(&json).unwrap();
+        assert_eq!(pattern.model.vertices, crease_pattern.vertices);
+        assert_eq!(pattern.model.triangles, crease_pattern.triangles);
+        assert_eq!(pattern.model.lines.len(), 5);
+        let diagonal = pattern
+            .model
+            .lines
+            .iter()
+            .position(|&(a, b, _, _)| (a, b) == (0, 2))
+            .unwrap();
+        assert_eq!(pattern.assignments[diagonal], EdgeAssignment::Valley);
+        assert_eq!(
+            pattern
+                .assignments
+                .iter()
+                .filter(|&&a| a == EdgeAssignment::Border)
+                .count(),
+            4
+        );
+
+        let pattern = FoldPattern::from_json_frame(&json, 1).unwrap();
+        assert_eq!(pattern.model.vertices, folded.vertices);
+
+        folded.vertices[3] = Vec3::new(0.0, 0.0, -1.0);
+        let json = export(&crease_pattern, &folded).unwrap();
+        let pattern = FoldPattern::from_json(&json).unwrap();
+        assert_eq!(pattern.assignments[diagonal], EdgeAssignment::Mountain);
+
+        // Flat creases keep the type of their line
+        folded.vertices[3] = Vec3::new(0.0, 1.0, 0.0);
+        let json = export(&crease_pattern, &folded).unwrap();
+        let pattern = FoldPattern::from_json(&json).unwrap();
+        assert_eq!(pattern.assignments[diagonal], EdgeAssignment::Valley);
+
+        folded.vertices.pop();
+        assert_eq!(
+            export(&crease_pattern, &folded).unwrap_err().to_string(),
+            "crease pattern has 4 vertices, but folded state has 3"
+        );
+    }
+
+    #[test]
+    fn test_export_animated() {
+        let model = FoldPattern::from_json(SQUARE)
+            .unwrap()
+            .model
+            .animate(1.0, Fold::new((0, 2), BitSet::with_bits(&[3]), PI / 2.0));
+        let json = export_animated(&model, 1.0).unwrap();
+
+        let pattern = FoldPattern::from_json(&json).unwrap();
+        assert_eq!(pattern.model.vertices, model.get_model(0.0).vertices);
+        let pattern = FoldPattern::from_json_frame(&json, 1).unwrap();
+        assert_eq!(pattern.model.vertices, model.get_model(1.0).vertices);
+    }
+}
@@ -0,0 +1,755 @@
+use crate::bit_set::BitSet;
+use crate::model::transform::fold::{Fold, FoldError, FoldSide};
+use crate::model::transform::{RigidFold, RigidFoldError};
+use crate::model::{AnimatedModel, Crease, Model, Stay, Transform};
+use glissade::Easing;
+use glm::{Mat4, Vec3};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Fold sequence script, one command per line, `#` starts a comment.
+/// Angles are in degrees, vertex sets are comma separated indexes and inclusive
+/// ranges like `2,3,5-9`, lines are vertex pairs like `0-4`.
+///
+/// ```text
+/// step <duration> [easing <name> | easing bezier <x1> <y1> <x2> <y2>] [delay <time>] [hold <time>]
+///      [label <text>]
+/// fold <line> <angle> (<vertices> | from <vertex>) [bend <radius>]
+/// shift <x> <y> <z> <vertices>
+/// shift_all <x> <y> <z>
+/// rotate_x <angle>
+/// rotate <x> <y> <z> <angle> [around <x> <y> <z>]
+/// rotate_line <line> <angle>
+/// scale (<factor> | <x> <y> <z>)
+/// transform <m11> <m12> <m13> <m14> ... <m44>
+/// add_lines [mountain | valley | border | cut | guide] <line>...
+/// collapse <angle> [fixed <face>]
+/// unfold <duration>
+/// ```
+///
+/// The `label` of a step takes the rest of the line.
+/// Commands after a `step` are applied one after another during that step,
+/// `unfold` plays everything before it backwards.
+/// `rotate` turns the model around the axis through the origin or the `around` point,
+/// `transform` moves it by a matrix written row by row.
+/// Lines are guides unless another crease type is given.
+/// `fold ... from` moves the part connected to the vertex in the state before the step.
+/// `collapse` folds all mountain and valley lines at once with rigid faces, see `RigidFold`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptError {
+    UnknownCommand {
+        line: usize,
+        command: String,
+    },
+    /// Command other than `step` before the first step
+    NoStep {
+        line: usize,
+    },
+    MissingArgument {
+        line: usize,
+        command: String,
+    },
+    UnexpectedArgument {
+        line: usize,
+        argument: String,
+    },
+    InvalidNumber {
+        line: usize,
+        value: String,
+    },
+    InvalidLine {
+        line: usize,
+        value: String,
+    },
+    /// Vertex range that goes down, like `9-3`
+    InvalidRange {
+        line: usize,
+        value: String,
+    },
+    UnknownEasing {
+        line: usize,
+        easing: String,
+    },
+    VertexOutOfRange {
+        line: usize,
+        vertex: u32,
+    },
+    Fold {
+        line: usize,
+        error: FoldError,
+    },
+    RigidFold {
+        line: usize,
+        error: RigidFoldError,
+    },
+}
+
+impl ScriptError {
+    pub fn line(&self) -> usize {
+        match *self {
+            ScriptError::UnknownCommand { line, .. }
+            | ScriptError::NoStep { line }
+            | ScriptError::MissingArgument { line, .. }
+            | ScriptError::UnexpectedArgument { line, .. }
+            | ScriptError::InvalidNumber { line, .. }
+            | ScriptError::InvalidLine { line, .. }
+            | ScriptError::InvalidRange { line, .. }
+            | ScriptError::UnknownEasing { line, .. }
+            | ScriptError::VertexOutOfRange { line, .. }
+            | ScriptError::Fold { line, .. }
+            | ScriptError::RigidFold { line, .. } => line,
+        }
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line())?;
+        match self {
+            ScriptError::UnknownCommand { command, .. } => {
+                write!(f, "unknown command \"{}\"", command)
+            }
+            ScriptError::NoStep { .. } => write!(f, "command before the first step"),
+            ScriptError::MissingArgument { command, .. } => {
+                write!(f, "\"{}\" needs more arguments", command)
+            }
+            ScriptError::UnexpectedArgument { argument, .. } => {
+                write!(f, "unexpected argument \"{}\"", argument)
+            }
+            ScriptError::InvalidNumber { value, .. } => {
+                write!(f, "\"{}\" is not a number", value)
+            }
+            ScriptError::InvalidLine { value, .. } => {
+                write!(
+                    f,
+                    "\"{}\" is not a line, expected two vertices like 0-4",
+                    value
+                )
+            }
+            ScriptError::InvalidRange { value, .. } => {
+                write!(
+                    f,
+                    "range \"{}\" goes down, expected the lower vertex first",
+                    value
+                )
+            }
+            ScriptError::UnknownEasing { easing, .. } => {
+                write!(f, "unknown easing \"{}\"", easing)
+            }
+            ScriptError::VertexOutOfRange { vertex, .. } => {
+                write!(f, "vertex {} is out of the model", vertex)
+            }
+            ScriptError::Fold { error, .. } => write!(f, "{}", error),
+            ScriptError::RigidFold { error, .. } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+/// Arguments of a command with the line they are on, for error messages
+struct Arguments<'a> {
+    line: usize,
+    command: &'a str,
+    tokens: &'a [&'a str],
+    vertex_count: u32,
+}
+
+impl<'a> Arguments<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.first().copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, ScriptError> {
+        match self.tokens.split_first() {
+            Some((&token, rest)) => {
+                self.tokens = rest;
+                Ok(token)
+            }
+            None => Err(ScriptError::MissingArgument {
+                line: self.line,
+                command: self.command.to_string(),
+            }),
+        }
+    }
+
+    /// Remaining tokens joined by spaces
+    fn rest(&mut self) -> Result<String, ScriptError> {
+        if self.tokens.is_empty() {
+            return Err(ScriptError::MissingArgument {
+                line: self.line,
+                command: self.command.to_string(),
+            });
+        }
+        let rest = self.tokens.join(" ");
+        self.tokens = &[];
+        Ok(rest)
+    }
+
+    fn end(&self) -> Result<(), ScriptError> {
+        match self.peek() {
+            Some(argument) => Err(ScriptError::UnexpectedArgument {
+                line: self.line,
+                argument: argument.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, ScriptError> {
+        let value = self.next()?;
+        value.parse().map_err(|_| ScriptError::InvalidNumber {
+            line: self.line,
+            value: value.to_string(),
+        })
+    }
+
+    fn angle(&mut self) -> Result<f32, ScriptError> {
+        Ok(self.number()?.to_radians())
+    }
+
+    fn vector(&mut self) -> Result<Vec3, ScriptError> {
+        Ok(Vec3::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    fn vertex_of(&self, value: &str) -> Result<u32, ScriptError> {
+        let vertex: u32 = value.parse().map_err(|_| ScriptError::InvalidNumber {
+            line: self.line,
+            value: value.to_string(),
+        })?;
+        if vertex >= self.vertex_count {
+            return Err(ScriptError::VertexOutOfRange {
+                line: self.line,
+                vertex,
+            });
+        }
+        Ok(vertex)
+    }
+
+    fn face(&mut self) -> Result<usize, ScriptError> {
+        let value = self.next()?;
+        value.parse().map_err(|_| ScriptError::InvalidNumber {
+            line: self.line,
+            value: value.to_string(),
+        })
+    }
+
+    fn vertex(&mut self) -> Result<u32, ScriptError> {
+        let value = self.next()?;
+        self.vertex_of(value)
+    }
+
+    fn line_of(&self, value: &str) -> Result<(u32, u32), ScriptError> {
+        match value.split_once('-') {
+            Some((a, b)) => Ok((self.vertex_of(a)?, self.vertex_of(b)?)),
+            None => Err(ScriptError::InvalidLine {
+                line: self.line,
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    fn fold_line(&mut self) -> Result<(u32, u32), ScriptError> {
+        let value = self.next()?;
+        self.line_of(value)
+    }
+
+    fn vertices(&mut self) -> Result<BitSet, ScriptError> {
+        let mut vertices = BitSet::new();
+        for item in self.next()?.split(',') {
+            match item.split_once('-') {
+                Some((from, to)) => {
+                    let (from, to) = (self.vertex_of(from)?, self.vertex_of(to)?);
+                    if from > to {
+                        return Err(ScriptError::InvalidRange {
+                            line: self.line,
+                            value: item.to_string(),
+                        });
+                    }
+                    for vertex in from..=to {
+                        vertices.insert(vertex);
+                    }
+                }
+                None => vertices.insert(self.vertex_of(item)?),
+            }
+        }
+        Ok(vertices)
+    }
+
+    fn easing(&mut self) -> Result<Easing, ScriptError> {
+        let name = self.next()?;
+        Ok(match name {
+            "linear" => Easing::Linear,
+            "quadratic_in" => Easing::QuadraticIn,
+            "quadratic_out" => Easing::QuadraticOut,
+            "quadratic_in_out" => Easing::QuadraticInOut,
+            "cubic_in" => Easing::CubicIn,
+            "cubic_out" => Easing::CubicOut,
+            "cubic_in_out" => Easing::CubicInOut,
+            "quartic_in" => Easing::QuarticIn,
+            "quartic_out" => Easing::QuarticOut,
+            "quartic_in_out" => Easing::QuarticInOut,
+            "none" => Easing::None,
+            "bezier" => Easing::bezier(
+                self.number()?,
+                self.number()?,
+                self.number()?,
+                self.number()?,
+            ),
+            _ => {
+                return Err(ScriptError::UnknownEasing {
+                    line: self.line,
+                    easing: name.to_string(),
+                })
+            }
+        })
+    }
+}
+
+struct Step {
+    duration: f32,
+    easing: Easing,
+    delay: f32,
+    hold: f32,
+    label: Option<String>,
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Step {
+    fn parse(arguments: &mut Arguments) -> Result<Step, ScriptError> {
+        let mut step = Step {
+            duration: arguments.number()?,
+            easing: Easing::QuadraticInOut,
+            delay: 0.0,
+            hold: 0.0,
+            label: None,
+            transforms: Vec::new(),
+        };
+        while let Some(option) = arguments.peek() {
+            arguments.next()?;
+            match option {
+                "easing" => step.easing = arguments.easing()?,
+                "delay" => step.delay = arguments.number()?,
+                "hold" => step.hold = arguments.number()?,
+                "label" => step.label = Some(arguments.rest()?),
+                _ => {
+                    return Err(ScriptError::UnexpectedArgument {
+                        line: arguments.line,
+                        argument: option.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(step)
+    }
+
+    fn finish(self, model: Box<dyn AnimatedModel>) -> Box<dyn AnimatedModel> {
+        let step = model
+            .animate(self.duration, self.transforms)
+            .easing(self.easing)
+            .delay(self.delay)
+            .hold(self.hold);
+        match self.label {
+            Some(label) => Box::new(step.label(&label)),
+            None => Box::new(step),
+        }
+    }
+}
+
+/// Build the animation described by the script, starting from `model`
+#[allow(dead_code)]
+pub fn parse(script: &str, model: Model) -> Result<Box<dyn AnimatedModel>, ScriptError> {
+    // Model at the end of the commands so far, vertices are checked against it
+    // as a step can add some, like the subdivision of `fold ... bend`
+    let mut state = model.clone();
+    let mut animated: Box<dyn AnimatedModel> = Box::new(model);
+    let mut step: Option<Step> = None;
+
+    for (i, text) in script.lines().enumerate() {
+        let line = i + 1;
+        let text = text.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let (&command, tokens) = match tokens.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+        let mut arguments = Arguments {
+            line,
+            command,
+            tokens,
+            vertex_count: state.vertices.len() as u32,
+        };
+
+        if command == "step" {
+            if let Some(step) = step.take() {
+                animated = step.finish(animated);
+            }
+            step = Some(Step::parse(&mut arguments)?);
+            continue;
+        }
+        if command == "unfold" {
+            if let Some(step) = step.take() {
+                animated = step.finish(animated);
+            }
+            let duration = arguments.number()?;
+            arguments.end()?;
+            animated = Box::new(animated.unfold(duration));
+            state = animated.get_model(animated.duration());
+            continue;
+        }
+
+        let current = step.as_mut().ok_or(ScriptError::NoStep { line })?;
+        let transform: Box<dyn Transform> = match command {
+            "fold" => {
+                let fold_line = arguments.fold_line()?;
+                let angle = arguments.angle()?;
+                let fold = if arguments.peek() == Some("from") {
+                    arguments.next()?;
+                    let side = FoldSide::Component(arguments.vertex()?);
+                    let model = animated.get_model(animated.duration());
+                    Fold::with_side(&model, fold_line, side, angle)
+                        .map_err(|error| ScriptError::Fold { line, error })?
+                } else {
+                    Fold::new(fold_line, arguments.vertices()?, angle)
+                };
+                if arguments.peek() == Some("bend") {
+                    arguments.next()?;
+                    Box::new(fold.bend(arguments.number()?))
+                } else {
+                    Box::new(fold)
+                }
+            }
+            "shift" => {
+                let shift = arguments.vector()?;
+                Box::new(Stay::new().shift(shift, arguments.vertices()?))
+            }
+            "shift_all" => Box::new(Stay::new().shift_all(arguments.vector()?)),
+            "rotate_x" => Box::new(Stay::new().rotate_x(arguments.angle()?)),
+            "rotate" => {
+                let direction = arguments.vector()?;
+                let angle = arguments.angle()?;
+                let pivot = if arguments.peek() == Some("around") {
+                    arguments.next()?;
+                    arguments.vector()?
+                } else {
+                    Vec3::zeros()
+                };
+                Box::new(Stay::new().rotate(direction, pivot, angle))
+            }
+            "rotate_line" => {
+                let line = arguments.fold_line()?;
+                Box::new(Stay::new().rotate_line(line, arguments.angle()?))
+            }
+            "scale" => {
+                let factor = arguments.number()?;
+                if arguments.peek().is_some() {
+                    let factors = Vec3::new(factor, arguments.number()?, arguments.number()?);
+                    Box::new(Stay::new().scale_axes(factors))
+                } else {
+                    Box::new(Stay::new().scale(factor))
+                }
+            }
+            "transform" => {
+                let mut values = [0.0; 16];
+                for value in values.iter_mut() {
+                    *value = arguments.number()?;
+                }
+                Box::new(Stay::new().transform(Mat4::from_row_slice(&values)))
+            }
+            "add_lines" => {
+                let crease = match arguments.peek() {
+                    Some("mountain") => Some(Crease::Mountain),
+                    Some("valley") => Some(Crease::Valley),
+                    Some("border") => Some(Crease::Border),
+                    Some("cut") => Some(Crease::Cut),
+                    Some("guide") => Some(Crease::Guide),
+                    _ => None,
+                };
+                if crease.is_some() {
+                    arguments.next()?;
+                }
+                let mut lines = vec![arguments.fold_line()?];
+                while arguments.peek().is_some() {
+                    lines.push(arguments.fold_line()?);
+                }
+                Box::new(Stay::new().add_lines(lines, crease.unwrap_or(Crease::Guide)))
+            }
+            "collapse" => {
+                let error = |error| ScriptError::RigidFold { line, error };
+                let collapse = RigidFold::creases(&state, arguments.angle()?).map_err(error)?;
+                if arguments.peek() == Some("fixed") {
+                    arguments.next()?;
+                    Box::new(collapse.fixed(arguments.face()?).map_err(error)?)
+                } else {
+                    Box::new(collapse)
+                }
+            }
+            _ => {
+                return Err(ScriptError::UnknownCommand {
+                    line,
+                    command: command.to_string(),
+                })
+            }
+        };
+        arguments.end()?;
+        state = transform.apply(state, 1.0);
+        current.transforms.push(transform);
+    }
+
+    if let Some(step) = step {
+        animated = step.finish(animated);
+    }
+    Ok(animated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3)],
+            lines: vec![],
+        }
+    }
+
+    fn parse_error(script: &str) -> ScriptError {
+        match parse(script, square()) {
+            Ok(_) => panic!("script is parsed"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn test_parse_steps() {
+        let model = parse(
+            "# Fold the corner and lift the square
+            step 1 easing linear
+            fold 0-2 180 1
+            add_lines mountain 0-2
+
+            step 0.5 delay 0.25 hold 0.25 label Lift the square
+            shift_all 0 0 1   # up
+            ",
+            square(),
+        )
+        .unwrap();
+
+        assert_eq!(model.duration(), 2.0);
+        assert_eq!(model.fold_steps().len(), 2);
+        assert_eq!(model.fold_steps()[0][0].line(), (0, 2));
+        let timeline = model.timeline();
+        assert_eq!(timeline.steps()[0].label, None);
+        let lift = &timeline.steps()[1];
+        assert_eq!(lift.label.as_deref(), Some("Lift the square"));
+        assert_eq!(
+            (lift.begin, lift.motion, lift.end),
+            (1.0, (1.25, 1.75), 2.0)
+        );
+
+        let half = model.get_model(0.5);
+        assert!((half.vertices[1] - Vec3::new(0.5, 0.5, -0.70710677)).norm() < 1e-5);
+        assert_eq!(half.lines, vec![(0, 2, 0.5, Crease::Mountain)]);
+
+        let end = model.get_model(2.0);
+        assert!((end.vertices[1] - Vec3::new(0.0, 1.0, 1.0)).norm() < 1e-5);
+        assert_eq!(end.vertices[3], Vec3::new(0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_unfold() {
+        let model = parse("step 1\nshift_all 0 0 1\nunfold 2\n", square()).unwrap();
+        assert_eq!(model.duration(), 3.0);
+        assert_eq!(model.get_model(1.0).vertices[0], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(model.get_model(3.0).vertices[0], Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            parse_error("step 1\nunfold 1\nrotate_x 90"),
+            ScriptError::NoStep { line: 3 }
+        );
+    }
+
+    #[test]
+    fn test_fold_from_vertex() {
+        let model = parse("step 1\nfold 0-2 90 from 3\n", square()).unwrap();
+        let end = model.get_model(1.0);
+        assert_eq!(end.vertices[1], Vec3::new(1.0, 0.0, 0.0));
+        assert!((end.vertices[3] - Vec3::new(0.5, 0.5, 0.70710677)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_fold_bend() {
+        let model = parse("step 1\nfold 0-2 90 from 3 bend 0.5\n", square()).unwrap();
+        assert_eq!(model.fold_steps()[0][0].line(), (0, 2));
+        let end = model.get_model(1.0);
+        assert!((end.vertices[3] - Vec3::new(0.5, 0.5, 0.70710677)).norm() < 1e-5);
+        // The paper near the crease is split, so the bend has vertices to curve
+        let bent = model.get_model(0.5);
+        assert!(bent.vertices.len() > 4);
+        assert!(bent.validate().is_valid(), "{}", bent.validate());
+        let middle = parse("step 1\nfold 0-2 90 from 3\n", square())
+            .unwrap()
+            .get_model(0.5);
+        assert!((model.get_model(0.5).vertices[3] - middle.vertices[3]).norm() > 1e-3);
+
+        // Later commands can use the vertices the bend adds
+        let last = model.get_model(1.0).vertices.len() - 1;
+        let script = format!(
+            "step 1\nfold 0-2 90 from 3 bend 0.5\nadd_lines 0-{}\nshift 0 0 1 {}",
+            last, last
+        );
+        let model = parse(&script, square()).unwrap();
+        assert_eq!(model.get_model(1.0).lines.last().unwrap().1, last as u32);
+        assert_eq!(
+            parse_error(&format!("step 1\nadd_lines 0-{}", last)),
+            ScriptError::VertexOutOfRange {
+                line: 2,
+                vertex: last as u32
+            }
+        );
+    }
+
+    #[test]
+    fn test_collapse() {
+        let model = parse(
+            "step 1\nadd_lines valley 0-2\nstep 1\ncollapse 90 fixed 0",
+            square(),
+        )
+        .unwrap();
+        let end = model.get_model(2.0);
+        for (a, b) in end.vertices.iter().zip(square().vertices.iter()).take(3) {
+            assert!((a - b).norm() < 1e-3, "{} != {}", a, b);
+        }
+        assert!(end.vertices[3].z.abs() > 0.5, "{}", end.vertices[3]);
+        assert_eq!(
+            parse_error("step 1\ncollapse 90 fixed 2"),
+            ScriptError::RigidFold {
+                line: 2,
+                error: RigidFoldError::FaceOutOfRange { face: 2 }
+            }
+        );
+    }
+
+    #[test]
+    fn test_rigid_transforms() {
+        let model = parse(
+            "step 1
+            rotate 0 0 1 90 around 1 0 0
+            scale 2 1 1
+            step 1
+            rotate_line 0-1 180
+            scale 0.5",
+            square(),
+        )
+        .unwrap();
+        let turned = model.get_model(1.0);
+        assert!((turned.vertices[0] - Vec3::new(2.0, -1.0, 0.0)).norm() < 1e-5);
+        let end = model.get_model(2.0);
+        assert!((end.vertices[3] - Vec3::new(2.0, -0.5, 0.0)).norm() < 1e-5);
+
+        let model = parse(
+            "step 1\ntransform 1 0 0 2  0 1 0 0  0 0 1 0  0 0 0 1",
+            square(),
+        )
+        .unwrap();
+        assert_eq!(model.get_model(0.5).vertices[0], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            parse_error("step 1\ntransform 1 0 0 2"),
+            ScriptError::MissingArgument {
+                line: 2,
+                command: "transform".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_vertex_sets() {
+        let model = parse("step 1\nshift 0 0 1 0,2-3\n", square()).unwrap();
+        let z: Vec<f32> = model.get_model(1.0).vertices.iter().map(|v| v.z).collect();
+        assert_eq!(z, vec![1.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse_error("fold 0-2 90 1"),
+            ScriptError::NoStep { line: 1 }
+        );
+        assert_eq!(
+            parse_error("step 1\n\nturn 90"),
+            ScriptError::UnknownCommand {
+                line: 3,
+                command: "turn".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nfold 0-2 ninety 1"),
+            ScriptError::InvalidNumber {
+                line: 2,
+                value: "ninety".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nfold 0-2 90"),
+            ScriptError::MissingArgument {
+                line: 2,
+                command: "fold".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nadd_lines 0-7"),
+            ScriptError::VertexOutOfRange { line: 2, vertex: 7 }
+        );
+        assert_eq!(
+            parse_error("step 1\nadd_lines mountain"),
+            ScriptError::MissingArgument {
+                line: 2,
+                command: "add_lines".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nadd_lines 0"),
+            ScriptError::InvalidLine {
+                line: 2,
+                value: "0".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nshift 0 0 1 3-1"),
+            ScriptError::InvalidRange {
+                line: 2,
+                value: "3-1".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1 easing bounce"),
+            ScriptError::UnknownEasing {
+                line: 1,
+                easing: "bounce".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nrotate_x 10 20"),
+            ScriptError::UnexpectedArgument {
+                line: 2,
+                argument: "20".to_string()
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nfold 0-2 90 from 0"),
+            ScriptError::Fold {
+                line: 2,
+                error: FoldError::AmbiguousSide
+            }
+        );
+        assert_eq!(
+            parse_error("step 1\nshift_all 0 0").to_string(),
+            "line 2: \"shift_all\" needs more arguments"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use crate::model::transform::fold::Fold;
+use crate::model::{AnimatedModel, Model};
+use glm::Vec3;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+/// Points closer than this to a plane or to the border of a triangle touch it
+const TOUCH_TOLERANCE: f32 = 1e-5;
+
+/// Two triangles passing through each other at some moment of the animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intersection {
+    pub triangles: (usize, usize),
+    pub time: f32,
+    /// Index of the step in `fold_steps` and its folds
+    pub step: Option<usize>,
+    pub folds: Vec<Fold>,
+}
+
+impl Display for Intersection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "triangles {} and {} pass through each other at time {}",
+            self.triangles.0, self.triangles.1, self.time
+        )?;
+        if let Some(step) = self.step {
+            write!(f, " in step {}", step)?;
+        }
+        for fold in self.folds.iter() {
+            write!(
+                f,
+                ", fold {:?} by {} degrees",
+                fold.line(),
+                fold.angle().to_degrees()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IntersectionReport {
+    /// The first time every pair of triangles is found intersecting
+    pub intersections: Vec<Intersection>,
+}
+
+impl IntersectionReport {
+    #[allow(dead_code)]
+    pub fn is_clean(&self) -> bool {
+        self.intersections.is_empty()
+    }
+}
+
+impl Display for IntersectionReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.intersections.is_empty() {
+            return write!(f, "no intersections");
+        }
+        for intersection in self.intersections.iter() {
+            writeln!(f, "{}", intersection)?;
+        }
+        Ok(())
+    }
+}
+
+/// The segment crosses the plane of the triangle strictly inside of it.
+/// Segments ending on the plane only touch it, as edges of neighbour faces do.
+fn segment_crosses(from: &Vec3, to: &Vec3, triangle: &[Vec3; 3]) -> bool {
+    let [a, b, c] = triangle;
+    let normal = (b - a).cross(&(c - a));
+    let area = normal.norm();
+    if area == 0.0 {
+        return false;
+    }
+    let normal = normal / area;
+    let (d1, d2) = ((from - a).dot(&normal), (to - a).dot(&normal));
+    if !(d1 > TOUCH_TOLERANCE && d2 < -TOUCH_TOLERANCE
+        || d1 < -TOUCH_TOLERANCE && d2 > TOUCH_TOLERANCE)
+    {
+        return false;
+    }
+    let point = from + (to - from) * (d1 / (d1 - d2));
+    // Distances of the point to the edges, positive inside
+    [(a, b), (b, c), (c, a)].iter().all(|(p, q)| {
+        let edge = *q - *p;
+        edge.cross(&(point - *p)).dot(&normal) / edge.norm() > TOUCH_TOLERANCE
+    })
+}
+
+fn corners(model: &Model, triangle: usize) -> [Vec3; 3] {
+    let (a, b, c) = model.triangles[triangle];
+    [
+        model.vertices[a as usize],
+        model.vertices[b as usize],
+        model.vertices[c as usize],
+    ]
+}
+
+/// Pairs of triangles of the model that pass through each other, the lower index first.
+/// Faces lying on one plane are stacked and never intersect, see `Layers`.
+pub fn intersecting(model: &Model) -> Vec<(usize, usize)> {
+    let triangles: Vec<[Vec3; 3]> = (0..model.triangles.len())
+        .map(|i| corners(model, i))
+        .collect();
+    let crosses = |first: &[Vec3; 3], second: &[Vec3; 3]| {
+        (0..3).any(|k| segment_crosses(&first[k], &first[(k + 1) % 3], second))
+    };
+    let mut pairs = Vec::new();
+    for (i, first) in triangles.iter().enumerate() {
+        for (j, second) in triangles.iter().enumerate().skip(i + 1) {
+            if crosses(first, second) || crosses(second, first) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/// Sample the animation at `samples` moments and find triangles passing through each other,
+/// like faces moved through the paper by folds in the wrong order
+#[allow(dead_code)]
+pub fn check_intersections(model: &dyn AnimatedModel, samples: usize) -> IntersectionReport {
+    let steps = model.fold_steps();
+    let mut found: HashSet<(usize, usize)> = HashSet::new();
+    let mut intersections = Vec::new();
+    for i in 0..=samples {
+        let time = if samples > 0 {
+            model.duration() * i as f32 / samples as f32
+        } else {
+            0.0
+        };
+        for pair in intersecting(&model.get_model(time)) {
+            if !found.insert(pair) {
+                continue;
+            }
+            let step = model.step_at(time);
+            intersections.push(Intersection {
+                triangles: pair,
+                time,
+                step,
+                folds: step
+                    .and_then(|step| steps.get(step))
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+    }
+    IntersectionReport { intersections }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Transform;
+    use std::f32::consts::PI;
+
+    /// Strip of three parts along x, the crease lines are at x = 1 and x = 2
+    /// and the right part is longer than the others
+    fn strip() -> Model {
+        let xs = [0.0, 1.0, 2.0, 3.5];
+        Model {
+            vertices: xs
+                .iter()
+                .flat_map(|&x| vec![Vec3::new(x, 0.0, 0.0), Vec3::new(x, 1.0, 0.0)])
+                .collect(),
+            triangles: vec![
+                (0, 2, 3),
+                (0, 3, 1),
+                (2, 4, 5),
+                (2, 5, 3),
+                (4, 6, 7),
+                (4, 7, 5),
+            ],
+            lines: vec![],
+        }
+    }
+
+    fn left_up() -> Fold {
+        Fold::new((2, 3), [0, 1].iter().copied().collect(), PI * 0.5)
+    }
+
+    fn right_over(angle: f32) -> Fold {
+        Fold::new((4, 5), [6, 7].iter().copied().collect(), angle)
+    }
+
+    #[test]
+    fn test_touching_faces_dont_intersect() {
+        assert!(intersecting(&strip()).is_empty());
+        let folded = Fold::new((2, 3), [0, 1].iter().copied().collect(), PI);
+        assert!(intersecting(&folded.apply(strip(), 1.0)).is_empty());
+    }
+
+    #[test]
+    fn test_fold_through_standing_part() {
+        // The left part stands up and the right part is folded over the top through it
+        let model = strip()
+            .animate(1.0, left_up())
+            .animate(1.0, right_over(-PI));
+        let report = check_intersections(&model, 40);
+        assert!(!report.is_clean());
+        let first = &report.intersections[0];
+        assert_eq!(first.step, Some(1));
+        assert_eq!(first.folds, vec![right_over(-PI)]);
+        assert!(first.time > 1.0 && first.time < 2.0);
+        assert!(first.triangles.0 < 2 && first.triangles.1 >= 4);
+        assert!(report
+            .to_string()
+            .contains("in step 1, fold (4, 5) by -180 degrees"));
+    }
+
+    #[test]
+    fn test_fold_under() {
+        let model = strip().animate(1.0, left_up()).animate(1.0, right_over(PI));
+        let report = check_intersections(&model, 40);
+        assert!(report.is_clean(), "{}", report);
+    }
+}
@@ -0,0 +1,430 @@
+use crate::model::adjacency::Adjacency;
+use crate::model::Model;
+use glm::{Vec2, Vec3};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::iter::FromIterator;
+
+/// Faces closer than this to the plane of another face are on that plane
+const PLANE_TOLERANCE: f32 = 1e-4;
+/// Faces with normals closer than this to parallel can lie on one plane
+const PARALLEL_TOLERANCE: f32 = 1e-4;
+/// Faces that only touch at the edges, up to this depth, don't overlap
+const OVERLAP_TOLERANCE: f32 = 1e-5;
+
+/// Order of faces in stacks of paper layers. Every face has a layer along its own normal,
+/// so the order stays valid when the stack is moved or turned over.
+/// Faces that are not in any stack have no layer.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Layers {
+    layers: Vec<Option<i32>>,
+}
+
+/// Region `other` is on the `side` of `region` along the normal of `region`,
+/// `flipped` is set when their normals point in opposite directions
+struct Contact {
+    region: usize,
+    other: usize,
+    side: f32,
+    flipped: bool,
+}
+
+fn normal(model: &Model, face: usize) -> Option<Vec3> {
+    let (a, b, c) = model.triangles[face];
+    let v = |i: u32| model.vertices[i as usize];
+    let normal = (v(b) - v(a)).cross(&(v(c) - v(a)));
+    if normal.norm() > 0.0 {
+        Some(normal.normalize())
+    } else {
+        None
+    }
+}
+
+fn centroid(model: &Model, face: usize) -> Vec3 {
+    let (a, b, c) = model.triangles[face];
+    let v = |i: u32| model.vertices[i as usize];
+    (v(a) + v(b) + v(c)) / 3.0
+}
+
+/// Corners of the face in a 2D basis of the plane with the `normal`
+fn flatten(model: &Model, face: usize, normal: &Vec3) -> [Vec2; 3] {
+    let (a, b, c) = model.triangles[face];
+    let u = if normal.x.abs() < 0.9 {
+        normal.cross(&Vec3::x())
+    } else {
+        normal.cross(&Vec3::y())
+    }
+    .normalize();
+    let v = normal.cross(&u);
+    let project = |i: u32| {
+        let p = model.vertices[i as usize];
+        Vec2::new(p.dot(&u), p.dot(&v))
+    };
+    [project(a), project(b), project(c)]
+}
+
+/// Separating axis test of two triangles, touching ones don't overlap
+fn overlap(first: &[Vec2; 3], second: &[Vec2; 3]) -> bool {
+    for triangle in [first, second].iter() {
+        for k in 0..3 {
+            let edge = triangle[(k + 1) % 3] - triangle[k];
+            let axis = Vec2::new(-edge.y, edge.x);
+            if axis.norm() == 0.0 {
+                return false;
+            }
+            let axis = axis.normalize();
+            let range = |points: &[Vec2; 3]| {
+                points
+                    .iter()
+                    .map(|p| p.dot(&axis))
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), d| {
+                        (min.min(d), max.max(d))
+                    })
+            };
+            let (min1, max1) = range(first);
+            let (min2, max2) = range(second);
+            if max1.min(max2) - min1.max(min2) <= OVERLAP_TOLERANCE {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Pairs of faces lying on one plane and overlapping, the lower index first
+pub fn stacked(model: &Model) -> Vec<(usize, usize)> {
+    let normals: Vec<Option<Vec3>> = (0..model.triangles.len())
+        .map(|face| normal(model, face))
+        .collect();
+    let mut pairs = Vec::new();
+    for a in 0..model.triangles.len() {
+        let na = match normals[a] {
+            Some(normal) => normal,
+            None => continue,
+        };
+        let origin = centroid(model, a);
+        for (b, nb) in normals.iter().enumerate().skip(a + 1) {
+            let nb = match nb {
+                Some(normal) => normal,
+                None => continue,
+            };
+            if 1.0 - na.dot(nb).abs() > PARALLEL_TOLERANCE {
+                continue;
+            }
+            let (i, j, k) = model.triangles[b];
+            let on_plane = [i, j, k]
+                .iter()
+                .all(|&v| (model.vertices[v as usize] - origin).dot(&na).abs() <= PLANE_TOLERANCE);
+            if on_plane && overlap(&flatten(model, a, &na), &flatten(model, b, &na)) {
+                pairs.push((a, b));
+            }
+        }
+    }
+    pairs
+}
+
+/// Flat parts of the model, faces joined by edges that are not folded get the same region
+fn regions(model: &Model) -> Vec<usize> {
+    fn root(parents: &mut [usize], face: usize) -> usize {
+        let mut face = face;
+        while parents[face] != face {
+            parents[face] = parents[parents[face]];
+            face = parents[face];
+        }
+        face
+    }
+
+    let adjacency = Adjacency::new(model);
+    let mut parents: Vec<usize> = (0..model.triangles.len()).collect();
+    for edge in adjacency.edges() {
+        if let Some((a, b)) = adjacency.sides(edge) {
+            if let (Some(na), Some(nb)) = (normal(model, a), normal(model, b)) {
+                if 1.0 - na.dot(&nb) <= PARALLEL_TOLERANCE {
+                    let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+                    parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+    }
+    (0..model.triangles.len())
+        .map(|face| root(&mut parents, face))
+        .collect()
+}
+
+impl Layers {
+    /// Layer of the face along its normal, if it's in a stack
+    pub fn get(&self, face: usize) -> Option<i32> {
+        self.layers.get(face).copied().flatten()
+    }
+
+    /// Layers at the end of a step that moves the model from `before` to `after`.
+    /// Stacks that exist before the step keep their order, faces that come together
+    /// during the step are stacked on the side they came from in the `approach` state,
+    /// a state shortly before the end of the step.
+    /// Transforms that change the triangles start a new order from the `after` state.
+    pub fn next(&self, before: &Model, approach: &Model, after: &Model) -> Layers {
+        let existing: HashSet<(usize, usize)> = if before.triangles == after.triangles {
+            stacked(before).into_iter().collect()
+        } else {
+            HashSet::new()
+        };
+        let approaching = approach.triangles == after.triangles;
+        let regions = regions(after);
+
+        let mut contacts = Vec::new();
+        for (a, b) in stacked(after) {
+            if regions[a] == regions[b] {
+                continue;
+            }
+            let flipped = match (normal(after, a), normal(after, b)) {
+                (Some(na), Some(nb)) => na.dot(&nb) < 0.0,
+                _ => continue,
+            };
+            let side = if existing.contains(&(a, b)) {
+                let flipped_before = match (normal(before, a), normal(before, b)) {
+                    (Some(na), Some(nb)) => na.dot(&nb) < 0.0,
+                    _ => continue,
+                };
+                if flipped_before != flipped {
+                    // One of the faces turned over and came back, the old order doesn't apply
+                    continue;
+                }
+                let (la, lb) = (self.get(a).unwrap_or(0), self.get(b).unwrap_or(0));
+                let height = if flipped { -lb } else { lb };
+                (height - la) as f32
+            } else if approaching {
+                // Where `b` comes from relative to `a`, along the normal of `a` at the end
+                let shift = |face: usize| centroid(approach, face) - centroid(after, face);
+                match normal(after, a) {
+                    Some(na) => (shift(b) - shift(a)).dot(&na),
+                    None => continue,
+                }
+            } else {
+                continue;
+            };
+            if side != 0.0 {
+                contacts.push(Contact {
+                    region: regions[a],
+                    other: regions[b],
+                    side,
+                    flipped,
+                });
+            }
+        }
+
+        let heights = Layers::solve(&contacts);
+        let layers = regions
+            .iter()
+            .map(|region| heights.get(region).copied())
+            .collect();
+        Layers { layers }
+    }
+
+    /// Layers of the regions in contact along their normals, the lowest regions
+    /// of every stack get 0. Contradicting contacts are ignored.
+    fn solve(contacts: &[Contact]) -> HashMap<usize, i32> {
+        let mut neighbours: HashMap<usize, Vec<(usize, bool)>> = HashMap::new();
+        for contact in contacts.iter() {
+            neighbours
+                .entry(contact.region)
+                .or_default()
+                .push((contact.other, contact.flipped));
+            neighbours
+                .entry(contact.other)
+                .or_default()
+                .push((contact.region, contact.flipped));
+        }
+
+        // Direction of every region normal in the frame of its stack
+        let mut orientation: HashMap<usize, i32> = HashMap::new();
+        let mut starts: Vec<usize> = neighbours.keys().copied().collect();
+        starts.sort_unstable();
+        for start in starts {
+            if orientation.contains_key(&start) {
+                continue;
+            }
+            orientation.insert(start, 1);
+            let mut queue = VecDeque::from(vec![start]);
+            while let Some(region) = queue.pop_front() {
+                let sign = orientation[&region];
+                for &(other, flipped) in neighbours[&region].iter() {
+                    if let Entry::Vacant(entry) = orientation.entry(other) {
+                        entry.insert(if flipped { -sign } else { sign });
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        // Region below -> regions above it, in the frame of the stack
+        let mut above: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut below_count: HashMap<usize, usize> = HashMap::new();
+        for contact in contacts.iter() {
+            let (region, other) = (contact.region, contact.other);
+            let expected = orientation[&region] * if contact.flipped { -1 } else { 1 };
+            if orientation[&other] != expected {
+                continue;
+            }
+            let (lower, upper) = if contact.side * orientation[&region] as f32 > 0.0 {
+                (region, other)
+            } else {
+                (other, region)
+            };
+            above.entry(lower).or_default().push(upper);
+            *below_count.entry(upper).or_default() += 1;
+        }
+
+        let mut heights: HashMap<usize, i32> = orientation.keys().map(|&r| (r, 0)).collect();
+        let mut queue: VecDeque<usize> = orientation
+            .keys()
+            .copied()
+            .filter(|region| !below_count.contains_key(region))
+            .collect();
+        while let Some(region) = queue.pop_front() {
+            let height = heights[&region] + 1;
+            for &upper in above.get(&region).into_iter().flatten() {
+                let entry = heights.get_mut(&upper).unwrap();
+                *entry = (*entry).max(height);
+                let count = below_count.get_mut(&upper).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    queue.push_back(upper);
+                }
+            }
+        }
+
+        heights
+            .into_iter()
+            .map(|(region, height)| (region, height * orientation[&region]))
+            .collect()
+    }
+
+    /// Layers of `count` copies of a model with `faces` triangles, merged one after another
+    pub fn repeat(&self, faces: usize, count: usize) -> Layers {
+        (0..faces * count)
+            .map(|face| self.get(face % faces))
+            .collect()
+    }
+
+    /// Offsets of the faces along their normals that move the layers `gap` apart,
+    /// faces that are not in any stack stay in place
+    pub fn offsets(&self, model: &Model, gap: f32) -> Vec<Vec3> {
+        (0..model.triangles.len())
+            .map(|face| match (self.get(face), normal(model, face)) {
+                (Some(layer), Some(normal)) => normal * (layer as f32 * gap),
+                _ => Vec3::zeros(),
+            })
+            .collect()
+    }
+}
+
+/// Layers of the faces in order
+impl FromIterator<Option<i32>> for Layers {
+    fn from_iter<I: IntoIterator<Item = Option<i32>>>(iter: I) -> Self {
+        Layers {
+            layers: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::transform::fold::Fold;
+    use crate::model::{AnimatedModel, Stay, Transform};
+    use glissade::Easing;
+    use std::f32::consts::PI;
+
+    /// Strip of two squares, the crease is at x = 1 and the right square moves
+    fn strip() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(2.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3), (1, 4, 5), (1, 5, 2)],
+            lines: vec![],
+        }
+    }
+
+    fn fold(angle: f32) -> Fold {
+        Fold::new((1, 2), [4, 5].iter().copied().collect(), angle)
+    }
+
+    /// Height of the moving square over the static one, along z
+    fn moving_above(layers: &Layers, model: &Model) -> f32 {
+        let offsets = layers.offsets(model, 1.0);
+        offsets[2].z - offsets[0].z
+    }
+
+    #[test]
+    fn test_stacked() {
+        assert!(stacked(&strip()).is_empty());
+        let folded = fold(PI).apply(strip(), 1.0);
+        let pairs = stacked(&folded);
+        assert!(pairs.contains(&(0, 3)));
+        assert!(pairs.contains(&(1, 2)));
+        assert!(!pairs.contains(&(0, 1)));
+    }
+
+    #[test]
+    fn test_flat_model_has_no_layers() {
+        let model = strip().animate(1.0, Stay::new());
+        let layers = model.layers(1.0);
+        assert_eq!(layers.get(0), None);
+        assert!(layers
+            .offsets(&model.get_model(1.0), 0.1)
+            .iter()
+            .all(|offset| *offset == Vec3::zeros()));
+    }
+
+    #[test]
+    fn test_folded_side_on_top() {
+        for &angle in [PI, -PI].iter() {
+            let model = strip().animate(1.0, fold(angle));
+            let folded = model.get_model(1.0);
+            let layers = model.layers(1.0);
+            // The moving square comes from the side it was on just before the end
+            let coming_from = fold(angle).apply(strip(), 0.9).vertices[4].z;
+            let above = moving_above(&layers, &folded);
+            assert!(above != 0.0);
+            assert_eq!(above.signum(), coming_from.signum(), "angle {}", angle);
+            // The faces are not stacked before the end of the fold
+            assert_eq!(model.layers(0.25), Layers::default());
+        }
+    }
+
+    /// Fold that is done in the first quarter of the step
+    struct Quick(Fold);
+
+    impl Transform for Quick {
+        fn apply(&self, model: Model, t: f32) -> Model {
+            self.0.apply(model, (t * 4.0).min(1.0))
+        }
+    }
+
+    #[test]
+    fn test_layers_when_faces_touch() {
+        let model = strip().animate(1.0, Quick(fold(PI))).easing(Easing::Linear);
+        assert_eq!(model.layers(0.2), Layers::default());
+        // The faces touch before the middle of the step
+        let touching = model.get_model(0.3);
+        assert!(!stacked(&touching).is_empty());
+        assert!(moving_above(&model.layers(0.3), &touching) != 0.0);
+        assert_eq!(model.layers(0.3), model.layers(1.0));
+    }
+
+    #[test]
+    fn test_order_kept_when_turned_over() {
+        let folded = strip().animate(1.0, fold(PI));
+        let above = moving_above(&folded.layers(1.0), &folded.get_model(1.0));
+        let turned = folded.animate(1.0, Stay::new().rotate_x(PI));
+        let model = turned.get_model(2.0);
+        // Offsets follow the turned normals, so the order along z is inverted
+        assert_eq!(moving_above(&turned.layers(2.0), &model), -above);
+    }
+}
@@ -1,8 +1,29 @@
+pub mod adjacency;
 mod animated_model;
+pub mod baked;
+mod creases;
+pub mod layers;
 mod model;
+mod playback;
+mod subdivision;
+mod symmetry;
+pub mod timeline;
 pub mod transform;
 
+// Tooling for plane designs, not used by the animation itself
+pub mod flat_foldability;
+pub mod fold_file;
+pub mod fold_script;
+pub mod intersection;
+pub mod svg_file;
+pub mod triangulation;
+pub mod validation;
+
 pub use animated_model::AnimatedModel;
-pub use model::Model;
+pub use model::{Crease, Model};
+pub use playback::{Reversed, Unfold};
+#[allow(unused_imports)]
+pub use symmetry::{Copies, OnCopies};
+pub use symmetry::{Symmetric, Symmetry};
 pub use transform::Stay;
 pub use transform::Transform;
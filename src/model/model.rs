@@ -1,36 +1,164 @@
 use glm::{Mat4, Vec3};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone)]
+/// Kind of a line drawn on the paper. Mountain and valley folds are seen from the front
+/// side of the paper, which the normals point to, as in the FOLD format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Crease {
+    Mountain,
+    Valley,
+    /// Edge of the paper
+    Border,
+    Cut,
+    /// Line that is not folded, like a reference for a later fold
+    Guide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Model {
     pub vertices: Vec<Vec3>,
-    pub triangles: Vec<(u8, u8, u8)>,
-    pub lines: Vec<(u8, u8, f32)>,
+    pub triangles: Vec<(u32, u32, u32)>,
+    /// Lines between two vertices with their opacity and crease type
+    pub lines: Vec<(u32, u32, f32, Crease)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeError {
+    /// Merged model has more vertices than `u32` indices can address
+    IndexOverflow { vertex_count: usize },
 }
 
+impl Display for MergeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::IndexOverflow { vertex_count } => write!(
+                f,
+                "merged model has {} vertices, which is more than u32 indices can address",
+                vertex_count
+            ),
+        }
+    }
+}
+
+impl Error for MergeError {}
+
 impl Model {
-    pub fn merge(self, other: Self) -> Model {
+    pub fn merge(self, other: Self) -> Result<Model, MergeError> {
+        let vertex_count = self.vertices.len() + other.vertices.len();
+        let overflow = MergeError::IndexOverflow { vertex_count };
+        // Checks the last index, as `u32::MAX as usize + 1` overflows a 32-bit `usize`
+        if vertex_count > 0 && u32::try_from(vertex_count - 1).is_err() {
+            return Err(overflow);
+        }
+
         let mut vertices = self.vertices;
         let mut triangles = self.triangles;
         let mut lines = self.lines;
-        let offset = vertices.len() as u8;
+        let offset = u32::try_from(vertices.len()).map_err(|_| overflow)?;
+        let shift = |i: u32| i.checked_add(offset).ok_or(overflow);
+
         vertices.extend(other.vertices);
-        triangles.extend(
-            other
-                .triangles
-                .iter()
-                .map(|(a, b, c)| (a + offset, b + offset, c + offset)),
-        );
-        lines.extend(
-            other
-                .lines
-                .iter()
-                .copied()
-                .map(|(a, b, alpha)| (a + offset, b + offset, alpha)),
-        );
-        Self {
+        for (a, b, c) in other.triangles {
+            triangles.push((shift(a)?, shift(b)?, shift(c)?));
+        }
+        for (a, b, alpha, crease) in other.lines {
+            lines.push((shift(a)?, shift(b)?, alpha, crease));
+        }
+
+        Ok(Self {
             vertices,
             triangles,
             lines,
+        })
+    }
+
+    /// Join vertices closer than `tolerance` into one, remapping triangles and lines.
+    /// Triangles and lines that collapse into a point or a segment are removed.
+    #[allow(dead_code)]
+    pub fn weld(self, tolerance: f32) -> Self {
+        let map = self.weld_map(tolerance);
+        self.reindex(&map)
+    }
+
+    /// New index of every vertex after welding, use with `reindex` to apply
+    /// the same welding to other states of the model
+    pub fn weld_map(&self, tolerance: f32) -> Vec<u32> {
+        let cell_size = tolerance.max(f32::EPSILON);
+        let cell = |v: &Vec3| {
+            (
+                (v.x / cell_size).floor() as i64,
+                (v.y / cell_size).floor() as i64,
+                (v.z / cell_size).floor() as i64,
+            )
+        };
+
+        let mut grid: HashMap<(i64, i64, i64), Vec<u32>> = HashMap::new();
+        let mut kept: Vec<Vec3> = Vec::new();
+        let mut map: Vec<u32> = Vec::with_capacity(self.vertices.len());
+
+        for vertex in self.vertices.iter() {
+            let (x, y, z) = cell(vertex);
+            let mut found = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        if let Some(candidates) = grid.get(&(x + dx, y + dy, z + dz)) {
+                            if let Some(&i) = candidates
+                                .iter()
+                                .find(|&&i| glm::distance(&kept[i as usize], vertex) <= tolerance)
+                            {
+                                found = Some(i);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let index = found.unwrap_or_else(|| {
+                let index = kept.len() as u32;
+                kept.push(*vertex);
+                grid.entry((x, y, z)).or_default().push(index);
+                index
+            });
+            map.push(index);
+        }
+
+        map
+    }
+
+    /// Move vertex `i` to index `map[i]`, when several vertices get the same index
+    /// the first one is kept. Triangles and lines that collapse are removed.
+    pub fn reindex(self, map: &[u32]) -> Self {
+        let count = map.iter().map(|&i| i as usize + 1).max().unwrap_or(0);
+        let mut vertices: Vec<Option<Vec3>> = vec![None; count];
+        for (vertex, &i) in self.vertices.iter().zip(map.iter()) {
+            vertices[i as usize].get_or_insert(*vertex);
+        }
+
+        let triangles = self
+            .triangles
+            .iter()
+            .map(|&(a, b, c)| (map[a as usize], map[b as usize], map[c as usize]))
+            .filter(|&(a, b, c)| a != b && b != c && c != a)
+            .collect();
+        let lines = self
+            .lines
+            .iter()
+            .map(|&(a, b, alpha, crease)| (map[a as usize], map[b as usize], alpha, crease))
+            .filter(|&(a, b, _, _)| a != b)
+            .collect();
+
+        Self {
+            vertices: vertices
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect(),
+            triangles,
+            lines,
         }
     }
 
@@ -41,9 +169,75 @@ impl Model {
         }
         Self { vertices, ..self }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn half() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+            ],
+            triangles: vec![(0, 2, 1)],
+            lines: vec![(0, 1, 1.0, Crease::Valley), (0, 2, 1.0, Crease::Border)],
+        }
+    }
+
+    /// `half` mirrored along x
+    fn mirrored() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(-1.0, 0.0, 0.0),
+            ],
+            ..half()
+        }
+    }
 
-    pub fn flip_x(self) -> Self {
-        let matrix = glm::scaling(&Vec3::new(-1.0, 1.0, 1.0));
-        self.transform(matrix)
+    #[test]
+    fn test_merge() {
+        let model = half().merge(mirrored()).unwrap();
+        assert_eq!(model.vertices.len(), 6);
+        assert_eq!(model.triangles, vec![(0, 2, 1), (3, 5, 4)]);
+        assert_eq!(model.lines[3], (3, 5, 1.0, Crease::Border));
+    }
+
+    #[test]
+    fn test_merge_welded() {
+        let model = half().merge(mirrored()).unwrap().weld(1e-5);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.vertices[3], Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(model.triangles, vec![(0, 2, 1), (0, 3, 1)]);
+        assert_eq!(
+            model.lines,
+            vec![
+                (0, 1, 1.0, Crease::Valley),
+                (0, 2, 1.0, Crease::Border),
+                (0, 1, 1.0, Crease::Valley),
+                (0, 3, 1.0, Crease::Border)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_weld_removes_collapsed() {
+        let model = Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 0.001),
+                Vec3::new(1.0, 0.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2)],
+            lines: vec![(0, 1, 1.0, Crease::Guide), (1, 2, 1.0, Crease::Guide)],
+        }
+        .weld(0.01);
+        assert_eq!(model.vertices.len(), 2);
+        assert!(model.triangles.is_empty());
+        assert_eq!(model.lines, vec![(0, 1, 1.0, Crease::Guide)]);
     }
 }
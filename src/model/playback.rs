@@ -0,0 +1,244 @@
+use crate::model::layers::Layers;
+use crate::model::timeline::{Step, Timeline};
+use crate::model::transform::fold::Fold;
+use crate::model::{AnimatedModel, Model};
+
+/// Animation played backwards, from the last state to the first one
+pub struct Reversed<M: AnimatedModel> {
+    model: M,
+}
+
+impl<M: AnimatedModel> Reversed<M> {
+    pub fn new(model: M) -> Self {
+        Self { model }
+    }
+}
+
+impl<M: AnimatedModel> AnimatedModel for Reversed<M> {
+    fn duration(&self) -> f32 {
+        self.model.duration()
+    }
+
+    fn get_model(&self, time: f32) -> Model {
+        let duration = self.model.duration();
+        self.model.get_model(duration - time.clamp(0.0, duration))
+    }
+
+    /// Steps in the reverse order, every fold undoes the original one
+    fn fold_steps(&self) -> Vec<Vec<Fold>> {
+        self.model
+            .fold_steps()
+            .iter()
+            .rev()
+            .map(|folds| folds.iter().rev().map(Fold::reversed).collect())
+            .collect()
+    }
+
+    fn layers(&self, time: f32) -> Layers {
+        let duration = self.model.duration();
+        self.model.layers(duration - time.clamp(0.0, duration))
+    }
+
+    fn step_at(&self, time: f32) -> Option<usize> {
+        let duration = self.model.duration();
+        let step = self.model.step_at(duration - time.clamp(0.0, duration))?;
+        Some(self.model.fold_steps().len() - 1 - step)
+    }
+
+    fn timeline(&self) -> Timeline {
+        let last = self.model.fold_steps().len().saturating_sub(1);
+        let mut timeline = Timeline::default();
+        for step in self
+            .model
+            .timeline()
+            .reversed(self.model.duration())
+            .steps()
+        {
+            timeline.push(Step {
+                index: last - step.index,
+                ..step.clone()
+            });
+        }
+        timeline
+    }
+}
+
+/// Animation followed by the same animation played backwards in `duration`,
+/// so the sheet unfolds and the sequence can loop
+pub struct Unfold<M: AnimatedModel> {
+    model: M,
+    duration: f32,
+}
+
+impl<M: AnimatedModel> Unfold<M> {
+    pub fn new(model: M, duration: f32) -> Self {
+        Self { model, duration }
+    }
+}
+
+impl<M: AnimatedModel> AnimatedModel for Unfold<M> {
+    fn duration(&self) -> f32 {
+        self.model.duration() + self.duration
+    }
+
+    fn get_model(&self, time: f32) -> Model {
+        let folding = self.model.duration();
+        if time < folding {
+            self.model.get_model(time)
+        } else if self.duration > 0.0 {
+            let t = ((time - folding) / self.duration).clamp(0.0, 1.0);
+            self.model.get_model(folding * (1.0 - t))
+        } else {
+            self.model.get_model(0.0)
+        }
+    }
+
+    fn layers(&self, time: f32) -> Layers {
+        let folding = self.model.duration();
+        if time < folding {
+            self.model.layers(time)
+        } else if self.duration > 0.0 {
+            let t = ((time - folding) / self.duration).clamp(0.0, 1.0);
+            self.model.layers(folding * (1.0 - t))
+        } else {
+            self.model.layers(0.0)
+        }
+    }
+
+    /// While unfolding, the step that is undone
+    fn step_at(&self, time: f32) -> Option<usize> {
+        let folding = self.model.duration();
+        if time < folding {
+            self.model.step_at(time)
+        } else if self.duration > 0.0 {
+            let t = ((time - folding) / self.duration).clamp(0.0, 1.0);
+            self.model.step_at(folding * (1.0 - t))
+        } else {
+            self.model.step_at(0.0)
+        }
+    }
+
+    /// Unfolding opens the existing creases, so there are no new fold steps
+    fn fold_steps(&self) -> Vec<Vec<Fold>> {
+        self.model.fold_steps()
+    }
+
+    /// Steps of the animation followed by the same steps played backwards while unfolding
+    fn timeline(&self) -> Timeline {
+        let folding = self.model.duration();
+        let mut timeline = self.model.timeline();
+        if folding > 0.0 {
+            let unfolding = timeline
+                .reversed(folding)
+                .scaled(self.duration / folding)
+                .shifted(folding);
+            for step in unfolding.steps() {
+                timeline.push(step.clone());
+            }
+        }
+        timeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_set::BitSet;
+    use crate::model::Stay;
+    use crate::model::Transform;
+    use glm::Vec3;
+
+    fn animated() -> impl AnimatedModel {
+        let model = Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2)],
+            lines: vec![],
+        };
+        model
+            .animate(
+                1.0,
+                Fold::new((0, 1), BitSet::with_bits(&[2]), std::f32::consts::PI),
+            )
+            .animate(2.0, Stay::new().shift_all(Vec3::z()))
+    }
+
+    #[test]
+    fn test_reversed() {
+        let model = animated();
+        let reversed = animated().reversed();
+        assert_eq!(reversed.duration(), 3.0);
+        for &time in [0.0, 0.5, 1.25, 3.0].iter() {
+            assert_eq!(
+                reversed.get_model(time).vertices,
+                model.get_model(3.0 - time).vertices
+            );
+        }
+
+        let steps = reversed.fold_steps();
+        assert_eq!(steps.len(), 2);
+        assert!(steps[0].is_empty());
+        assert_eq!(steps[1][0].angle(), -std::f32::consts::PI);
+        assert_eq!(model.step_at(0.5), Some(0));
+        assert_eq!(reversed.step_at(0.5), Some(0));
+        assert_eq!(reversed.step_at(2.5), Some(1));
+        let timeline = reversed.timeline();
+        for &time in [0.5, 2.5].iter() {
+            assert_eq!(timeline.at(time).map(|at| at.index), reversed.step_at(time));
+        }
+    }
+
+    #[test]
+    fn test_unfold() {
+        let model = animated();
+        let unfold = animated().unfold(1.5);
+        assert_eq!(unfold.duration(), 4.5);
+        assert_eq!(
+            unfold.get_model(2.0).vertices,
+            model.get_model(2.0).vertices
+        );
+        assert_eq!(
+            unfold.get_model(3.0).vertices,
+            model.get_model(3.0).vertices
+        );
+        assert_eq!(
+            unfold.get_model(3.75).vertices,
+            model.get_model(1.5).vertices
+        );
+        assert_eq!(
+            unfold.get_model(4.5).vertices,
+            model.get_model(0.0).vertices
+        );
+        assert_eq!(unfold.fold_steps(), model.fold_steps());
+        assert_eq!(unfold.step_at(3.75), Some(1));
+        assert_eq!(unfold.step_at(4.4), Some(0));
+    }
+
+    #[test]
+    fn test_unfold_timeline() {
+        let unfold = animated().unfold(1.5);
+        let timeline = unfold.timeline();
+        assert_eq!(timeline.steps().len(), 4);
+        assert_eq!(timeline.steps()[3].end, 4.5);
+        let baked = unfold.bake(90).unwrap();
+        for i in 0..45 {
+            let time = i as f32 * 0.1 + 0.05;
+            let at = timeline.at(time).map(|at| at.index);
+            assert_eq!(at, unfold.step_at(time), "time {}", time);
+            assert_eq!(baked.step_at(time), unfold.step_at(time), "time {}", time);
+        }
+    }
+
+    #[test]
+    fn test_chain_after_unfold() {
+        let model = animated()
+            .unfold(1.0)
+            .animate(1.0, Stay::new().shift_all(Vec3::x()));
+        let last = model.get_model(model.duration());
+        assert_eq!(last.vertices[0], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(last.vertices[2], Vec3::new(1.0, 1.0, 0.0));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::model::Model;
+use glm::Vec3;
+use std::collections::{HashMap, HashSet};
+
+/// Limit of refinement passes, every pass halves the edges
+const MAX_PASSES: usize = 16;
+
+fn distance_to_segment(point: &Vec3, from: &Vec3, to: &Vec3) -> f32 {
+    let direction = to - from;
+    let length = direction.norm_squared();
+    let s = if length > 0.0 {
+        ((point - from).dot(&direction) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (point - (from + direction * s)).norm()
+}
+
+impl Model {
+    /// Split edges longer than `max_length` that are closer than `distance` to the segment
+    /// from `from` to `to`, so bends near a crease have vertices to curve the paper.
+    /// Neighbour triangles are split at the same midpoints, so the mesh stays connected.
+    /// New vertices are added after the existing ones.
+    pub fn subdivide_near(self, from: Vec3, to: Vec3, distance: f32, max_length: f32) -> Model {
+        let mut model = self;
+        for _ in 0..MAX_PASSES {
+            let mut split: HashSet<(u32, u32)> = HashSet::new();
+            for &(a, b, c) in model.triangles.iter() {
+                for &(p, q) in [(a, b), (b, c), (c, a)].iter() {
+                    let (vp, vq) = (model.vertices[p as usize], model.vertices[q as usize]);
+                    let middle = (vp + vq) * 0.5;
+                    let near = [vp, vq, middle]
+                        .iter()
+                        .any(|v| distance_to_segment(v, &from, &to) <= distance);
+                    if near && (vq - vp).norm() > max_length {
+                        split.insert((p.min(q), p.max(q)));
+                    }
+                }
+            }
+            if split.is_empty() {
+                break;
+            }
+            model = model.split_edges(&split);
+        }
+        model
+    }
+
+    /// Split the edges at their midpoints, every triangle is divided into 2, 3 or 4 parts
+    fn split_edges(self, split: &HashSet<(u32, u32)>) -> Model {
+        let mut vertices = self.vertices;
+        let mut middles: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut middle = |a: u32, b: u32, vertices: &mut Vec<Vec3>| -> Option<u32> {
+            let key = (a.min(b), a.max(b));
+            if !split.contains(&key) {
+                return None;
+            }
+            Some(*middles.entry(key).or_insert_with(|| {
+                vertices.push((vertices[a as usize] + vertices[b as usize]) * 0.5);
+                vertices.len() as u32 - 1
+            }))
+        };
+
+        let mut triangles = Vec::with_capacity(self.triangles.len() * 2);
+        for &(a, b, c) in self.triangles.iter() {
+            let ab = middle(a, b, &mut vertices);
+            let bc = middle(b, c, &mut vertices);
+            let ca = middle(c, a, &mut vertices);
+            match (ab, bc, ca) {
+                (None, None, None) => triangles.push((a, b, c)),
+                (Some(ab), None, None) => triangles.extend(vec![(a, ab, c), (ab, b, c)]),
+                (None, Some(bc), None) => triangles.extend(vec![(b, bc, a), (bc, c, a)]),
+                (None, None, Some(ca)) => triangles.extend(vec![(c, ca, b), (ca, a, b)]),
+                (Some(ab), Some(bc), None) => {
+                    triangles.extend(vec![(ab, b, bc), (a, ab, bc), (a, bc, c)])
+                }
+                (None, Some(bc), Some(ca)) => {
+                    triangles.extend(vec![(bc, c, ca), (b, bc, ca), (b, ca, a)])
+                }
+                (Some(ab), None, Some(ca)) => {
+                    triangles.extend(vec![(ca, a, ab), (c, ca, ab), (c, ab, b)])
+                }
+                (Some(ab), Some(bc), Some(ca)) => {
+                    triangles.extend(vec![(a, ab, ca), (ab, b, bc), (ca, bc, c), (ab, bc, ca)])
+                }
+            }
+        }
+
+        let mut lines = Vec::with_capacity(self.lines.len());
+        for &(a, b, alpha, crease) in self.lines.iter() {
+            match middle(a, b, &mut vertices) {
+                Some(m) => {
+                    lines.push((a, m, alpha, crease));
+                    lines.push((m, b, alpha, crease));
+                }
+                None => lines.push((a, b, alpha, crease)),
+            }
+        }
+
+        Model {
+            vertices,
+            triangles,
+            lines,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Crease;
+
+    fn square() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3)],
+            lines: vec![(0, 1, 1.0, Crease::Border), (1, 2, 0.5, Crease::Border)],
+        }
+    }
+
+    fn area(model: &Model) -> f32 {
+        model
+            .triangles
+            .iter()
+            .map(|&(a, b, c)| {
+                let v = |i: u32| model.vertices[i as usize];
+                (v(b) - v(a)).cross(&(v(c) - v(a))).z * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_subdivide_near_crease() {
+        let from = Vec3::new(0.5, 0.0, 0.0);
+        let to = Vec3::new(0.5, 1.0, 0.0);
+        let model = square().subdivide_near(from, to, 0.1, 0.2);
+
+        assert!(model.validate().is_valid(), "{}", model.validate());
+        assert!((area(&model) - 1.0).abs() < 1e-5);
+        // Every triangle near the crease is small enough
+        for &(a, b, c) in model.triangles.iter() {
+            for &(p, q) in [(a, b), (b, c), (c, a)].iter() {
+                let (vp, vq) = (model.vertices[p as usize], model.vertices[q as usize]);
+                if distance_to_segment(&((vp + vq) * 0.5), &from, &to) <= 0.1 {
+                    assert!((vq - vp).norm() <= 0.2);
+                }
+            }
+        }
+        assert!(model.vertices.len() > 10);
+        let bottom: Vec<_> = model
+            .lines
+            .iter()
+            .filter(|&&(_, _, alpha, _)| alpha == 1.0)
+            .collect();
+        assert!(bottom.len() > 1);
+    }
+
+    #[test]
+    fn test_far_from_crease() {
+        let model =
+            square().subdivide_near(Vec3::new(5.0, 0.0, 0.0), Vec3::new(5.0, 1.0, 0.0), 0.1, 0.2);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.triangles, square().triangles);
+    }
+
+    #[test]
+    fn test_split_patterns() {
+        let split: HashSet<(u32, u32)> = [(0, 1), (1, 2)].iter().copied().collect();
+        let model = square().split_edges(&split);
+        assert_eq!(model.vertices.len(), 6);
+        assert_eq!(model.triangles.len(), 3 + 1);
+        assert!(model.validate().is_valid(), "{}", model.validate());
+        assert_eq!(model.lines.len(), 4);
+        assert!((area(&model) - 1.0).abs() < 1e-6);
+    }
+}
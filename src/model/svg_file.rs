@@ -0,0 +1,665 @@
+use crate::model::fold_file::{EdgeAssignment, FoldPattern};
+use crate::model::{AnimatedModel, Crease, Model};
+use glm::{Mat3, Mat4, Vec2, Vec3};
+use roxmltree::{Document, Node};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
+use svgtypes::{Color, SimplePathSegment, SimplifyingPathParser, Transform};
+
+/// Nested `<use>` elements deeper than this are treated as a reference cycle
+const MAX_DEPTH: usize = 32;
+/// Sum of fold angles along a line that counts as no fold, the line is unfolded
+const UNDONE_TOLERANCE: f32 = 1e-4;
+/// Distance in model units from a line that counts as on the line
+const ON_LINE_TOLERANCE: f32 = 1e-4;
+
+#[derive(Debug)]
+pub enum SvgFileError {
+    Xml(roxmltree::Error),
+    InvalidAttribute {
+        line: u32,
+        attribute: &'static str,
+        value: String,
+    },
+    CurvedPath {
+        line: u32,
+    },
+    MissingReference {
+        line: u32,
+        id: String,
+    },
+    /// Fold line of a crease pattern ends at a vertex added by the animation,
+    /// which has no place on the flat sheet
+    FoldOffSheet {
+        step: usize,
+        vertex: u32,
+    },
+}
+
+impl Display for SvgFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SvgFileError::Xml(error) => write!(f, "invalid SVG: {}", error),
+            SvgFileError::InvalidAttribute {
+                line,
+                attribute,
+                value,
+            } => write!(f, "line {}: invalid {} {:?}", line, attribute, value),
+            SvgFileError::CurvedPath { line } => {
+                write!(f, "line {}: curved path segments can't be creases", line)
+            }
+            SvgFileError::MissingReference { line, id } => {
+                write!(f, "line {}: element #{} not found", line, id)
+            }
+            SvgFileError::FoldOffSheet { step, vertex } => write!(
+                f,
+                "fold of step {} is on vertex {}, which isn't on the flat sheet",
+                step, vertex
+            ),
+        }
+    }
+}
+
+impl Error for SvgFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SvgFileError::Xml(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<roxmltree::Error> for SvgFileError {
+    fn from(error: roxmltree::Error) -> Self {
+        SvgFileError::Xml(error)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Style {
+    transform: Mat3,
+    stroke: Option<Color>,
+    class: Option<EdgeAssignment>,
+}
+
+#[derive(Clone, Copy)]
+struct Segment {
+    from: Vec2,
+    to: Vec2,
+    assignment: EdgeAssignment,
+}
+
+impl FoldPattern {
+    /// Import crease lines from `<line>`, `<path>` and `<rect>` elements of an SVG drawing.
+    ///
+    /// The crease type comes from a `mountain`, `valley`, `border`, `cut` or `flat` class
+    /// of the element or its groups, or from the stroke colour: red for mountain, blue
+    /// for valley and green for cut lines. Unstyled rectangles are sheet borders.
+    /// Crossing lines are split, and points closer than `tolerance` are joined.
+    /// Coordinates are in SVG user units, the model has no triangles.
+    #[allow(dead_code)]
+    pub fn from_svg(svg: &str, tolerance: f32) -> Result<FoldPattern, SvgFileError> {
+        let document = Document::parse(svg)?;
+        let style = Style {
+            transform: Mat3::identity(),
+            stroke: None,
+            class: None,
+        };
+        let mut segments = Vec::new();
+        collect(&document, document.root_element(), style, 0, &mut segments)?;
+        Ok(build(split(&segments, tolerance), tolerance))
+    }
+}
+
+fn line_of(document: &Document, node: Node) -> u32 {
+    document.text_pos_at(node.range().start).row
+}
+
+fn number(document: &Document, node: Node, attribute: &'static str) -> Result<f32, SvgFileError> {
+    match node.attribute(attribute) {
+        None => Ok(0.0),
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| SvgFileError::InvalidAttribute {
+                line: line_of(document, node),
+                attribute,
+                value: value.to_string(),
+            }),
+    }
+}
+
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let from_style = node.attribute("style").and_then(|style| {
+        style
+            .split(';')
+            .filter_map(|declaration| {
+                let mut parts = declaration.splitn(2, ':');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            })
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    });
+    from_style.or_else(|| node.attribute(name))
+}
+
+fn class_assignment(class: &str) -> Option<EdgeAssignment> {
+    class.split_whitespace().find_map(|name| match name {
+        "mountain" => Some(EdgeAssignment::Mountain),
+        "valley" => Some(EdgeAssignment::Valley),
+        "border" => Some(EdgeAssignment::Border),
+        "cut" => Some(EdgeAssignment::Cut),
+        "flat" => Some(EdgeAssignment::Flat),
+        _ => None,
+    })
+}
+
+fn color_assignment(color: Color) -> Option<EdgeAssignment> {
+    let (r, g, b) = (color.red as i32, color.green as i32, color.blue as i32);
+    let dominates = |c: i32, o1: i32, o2: i32| c >= 128 && c - o1.max(o2) >= 64;
+    if dominates(r, g, b) {
+        Some(EdgeAssignment::Mountain)
+    } else if dominates(b, r, g) {
+        Some(EdgeAssignment::Valley)
+    } else if dominates(g, r, b) {
+        Some(EdgeAssignment::Cut)
+    } else {
+        None
+    }
+}
+
+fn parse_transform(document: &Document, node: Node, value: &str) -> Result<Mat3, SvgFileError> {
+    let ts = Transform::from_str(value).map_err(|_| SvgFileError::InvalidAttribute {
+        line: line_of(document, node),
+        attribute: "transform",
+        value: value.to_string(),
+    })?;
+    Ok(Mat3::new(
+        ts.a as f32,
+        ts.c as f32,
+        ts.e as f32,
+        ts.b as f32,
+        ts.d as f32,
+        ts.f as f32,
+        0.0,
+        0.0,
+        1.0,
+    ))
+}
+
+fn collect(
+    document: &Document,
+    node: Node,
+    style: Style,
+    depth: usize,
+    segments: &mut Vec<Segment>,
+) -> Result<(), SvgFileError> {
+    let mut style = style;
+    if let Some(value) = node.attribute("transform") {
+        style.transform *= parse_transform(document, node, value)?;
+    }
+    if let Some(value) = property(node, "stroke") {
+        style.stroke = if value == "none" {
+            None
+        } else {
+            Some(
+                Color::from_str(value).map_err(|_| SvgFileError::InvalidAttribute {
+                    line: line_of(document, node),
+                    attribute: "stroke",
+                    value: value.to_string(),
+                })?,
+            )
+        };
+    }
+    if let Some(assignment) = node.attribute("class").and_then(class_assignment) {
+        style.class = Some(assignment);
+    }
+    if property(node, "stroke") == Some("none") || property(node, "display") == Some("none") {
+        return Ok(());
+    }
+
+    let point = |x: f32, y: f32| (style.transform * Vec3::new(x, y, 1.0)).xy();
+    let assignment = |default: EdgeAssignment| {
+        style
+            .class
+            .or_else(|| style.stroke.and_then(color_assignment))
+            .unwrap_or(default)
+    };
+    let mut add = |from: Vec2, to: Vec2, assignment: EdgeAssignment| {
+        segments.push(Segment {
+            from,
+            to,
+            assignment,
+        })
+    };
+
+    match node.tag_name().name() {
+        "line" => {
+            let from = point(number(document, node, "x1")?, number(document, node, "y1")?);
+            let to = point(number(document, node, "x2")?, number(document, node, "y2")?);
+            add(from, to, assignment(EdgeAssignment::Unassigned));
+        }
+        "rect" => {
+            let x = number(document, node, "x")?;
+            let y = number(document, node, "y")?;
+            let w = number(document, node, "width")?;
+            let h = number(document, node, "height")?;
+            let corners = [
+                point(x, y),
+                point(x + w, y),
+                point(x + w, y + h),
+                point(x, y + h),
+            ];
+            for i in 0..4 {
+                add(
+                    corners[i],
+                    corners[(i + 1) % 4],
+                    assignment(EdgeAssignment::Border),
+                );
+            }
+        }
+        "path" => {
+            let data = node.attribute("d").unwrap_or("");
+            let mut start = Vec2::zeros();
+            let mut current = Vec2::zeros();
+            for segment in SimplifyingPathParser::from(data) {
+                let segment = segment.map_err(|_| SvgFileError::InvalidAttribute {
+                    line: line_of(document, node),
+                    attribute: "path data",
+                    value: data.to_string(),
+                })?;
+                match segment {
+                    SimplePathSegment::MoveTo { x, y } => {
+                        current = point(x as f32, y as f32);
+                        start = current;
+                    }
+                    SimplePathSegment::LineTo { x, y } => {
+                        let to = point(x as f32, y as f32);
+                        add(current, to, assignment(EdgeAssignment::Unassigned));
+                        current = to;
+                    }
+                    SimplePathSegment::ClosePath => {
+                        add(current, start, assignment(EdgeAssignment::Unassigned));
+                        current = start;
+                    }
+                    SimplePathSegment::CurveTo { .. } | SimplePathSegment::Quadratic { .. } => {
+                        return Err(SvgFileError::CurvedPath {
+                            line: line_of(document, node),
+                        });
+                    }
+                }
+            }
+        }
+        "use" => {
+            let href = node
+                .attribute("href")
+                .or_else(|| node.attribute(("http://www.w3.org/1999/xlink", "href")))
+                .unwrap_or("");
+            let id = href.trim_start_matches('#');
+            let target = document
+                .descendants()
+                .find(|n| n.attribute("id") == Some(id));
+            match target {
+                Some(target) if depth < MAX_DEPTH => {
+                    let x = number(document, node, "x")?;
+                    let y = number(document, node, "y")?;
+                    style.transform *= glm::translation2d(&Vec2::new(x, y));
+                    collect(document, target, style, depth + 1, segments)?;
+                }
+                _ => {
+                    return Err(SvgFileError::MissingReference {
+                        line: line_of(document, node),
+                        id: id.to_string(),
+                    })
+                }
+            }
+        }
+        "defs" | "symbol" | "clipPath" | "mask" | "pattern" => {}
+        _ => {
+            for child in node.children().filter(Node::is_element) {
+                collect(document, child, style, depth, segments)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Split segments at the points where they cross or touch other segments
+fn split(segments: &[Segment], tolerance: f32) -> Vec<Segment> {
+    let mut result = Vec::new();
+    for (i, s) in segments.iter().enumerate() {
+        let direction = s.to - s.from;
+        let length = direction.norm();
+        if length <= tolerance {
+            continue;
+        }
+        let eps = tolerance / length;
+
+        let mut cuts = vec![0.0, 1.0];
+        for (j, o) in segments.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            // Endpoints of other segments that lie on this one
+            for p in [o.from, o.to].iter() {
+                let t = (p - s.from).dot(&direction) / (length * length);
+                if t > 0.0 && t < 1.0 && glm::distance(&(s.from + direction * t), p) <= tolerance {
+                    cuts.push(t);
+                }
+            }
+            // Proper crossings
+            let other = o.to - o.from;
+            let denominator = direction.perp(&other);
+            if denominator.abs() > f32::EPSILON {
+                let offset = o.from - s.from;
+                let t = offset.perp(&other) / denominator;
+                let u = offset.perp(&direction) / denominator;
+                let other_eps = tolerance / other.norm().max(f32::EPSILON);
+                if t > eps && t < 1.0 - eps && u >= -other_eps && u <= 1.0 + other_eps {
+                    cuts.push(t);
+                }
+            }
+        }
+
+        cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut last = 0.0;
+        for &t in cuts[1..].iter() {
+            if (t - last) * length > tolerance || t == 1.0 {
+                result.push(Segment {
+                    from: s.from + direction * last,
+                    to: s.from + direction * t,
+                    assignment: s.assignment,
+                });
+                last = t;
+            }
+        }
+    }
+    result
+}
+
+fn build(segments: Vec<Segment>, tolerance: f32) -> FoldPattern {
+    let points = Model {
+        vertices: segments
+            .iter()
+            .flat_map(|s| vec![s.from.push(0.0), s.to.push(0.0)])
+            .collect(),
+        triangles: vec![],
+        lines: vec![],
+    };
+    let map = points.weld_map(tolerance);
+
+    let mut lines: Vec<(u32, u32, f32, Crease)> = Vec::new();
+    let mut assignments = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let (a, b) = (map[i * 2], map[i * 2 + 1]);
+        let same = |&(c, d, _, _): &(u32, u32, f32, Crease)| (c, d) == (a, b) || (c, d) == (b, a);
+        if a != b && !lines.iter().any(same) {
+            lines.push((a, b, 1.0, segment.assignment.crease()));
+            assignments.push(segment.assignment);
+        }
+    }
+
+    FoldPattern {
+        model: Model {
+            lines,
+            ..points.reindex(&map)
+        },
+        assignments,
+    }
+}
+
+const CREASE_PATTERN_STYLE: &str = "
+    .border { fill: none; stroke: black; stroke-width: 0.3 }
+    .mountain { stroke: #d00000; stroke-width: 0.3; stroke-dasharray: 4 1 1 1 1 1 }
+    .valley { stroke: #0000d0; stroke-width: 0.3; stroke-dasharray: 2 1 }
+    text { font-family: monospace; font-size: 4px; text-anchor: middle; fill: black }
+";
+
+/// Printable crease pattern of the rest state of `model`, with every fold numbered
+/// in the fold order. Positive fold angles are valley folds, as the sheet is seen
+/// from the negative z side. `paper` is the sheet size in millimetres, and `copies`
+/// map the model to the sheet, e.g. a mirrored copy for symmetric designs.
+/// Creases run across the whole sheet, and folds that undo the earlier folds
+/// on the same line, like unfolding a pre-crease, are not drawn again.
+pub fn crease_pattern(
+    model: &dyn AnimatedModel,
+    paper: Vec2,
+    copies: &[Mat4],
+) -> Result<String, SvgFileError> {
+    let rest = model.get_model(0.0);
+    let mut svg = String::new();
+
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}mm" height="{h}mm" viewBox="0 0 {w} {h}">"#,
+        w = paper.x,
+        h = paper.y
+    )
+    .unwrap();
+    writeln!(svg, "<style>{}</style>", CREASE_PATTERN_STYLE).unwrap();
+    writeln!(
+        svg,
+        r#"<rect class="border" x="0" y="0" width="{}" height="{}"/>"#,
+        paper.x, paper.y
+    )
+    .unwrap();
+
+    // Lines folded so far with the sum of their fold angles
+    let mut turns: Vec<((Vec3, Vec3), f32)> = Vec::new();
+    let mut number = 0;
+    for (step, folds) in model.fold_steps().iter().enumerate() {
+        for fold in folds.iter().filter(|fold| fold.angle() != 0.0) {
+            let point = |vertex: u32| {
+                rest.vertices
+                    .get(vertex as usize)
+                    .copied()
+                    .ok_or(SvgFileError::FoldOffSheet { step, vertex })
+            };
+            let (a, b) = fold.line();
+            let (from, to) = (point(a)?, point(b)?);
+
+            let line = match turns
+                .iter()
+                .position(|&(line, _)| same_line(line, (from, to)))
+            {
+                Some(line) => line,
+                None => {
+                    turns.push(((from, to), 0.0));
+                    turns.len() - 1
+                }
+            };
+            let turn = turns[line].1;
+            turns[line].1 += fold.angle();
+            if turn != 0.0 && turns[line].1.abs() <= UNDONE_TOLERANCE {
+                continue;
+            }
+
+            number += 1;
+            let class = if fold.angle() > 0.0 {
+                "valley"
+            } else {
+                "mountain"
+            };
+            let crease = across_faces(&rest, from, to);
+            for copy in copies {
+                let point = |t: f32| (copy * (from + (to - from) * t).push(1.0)).xy();
+                for &(begin, end) in crease.iter() {
+                    let (begin, end) = (point(begin), point(end));
+                    writeln!(
+                        svg,
+                        r#"<line class="{}" x1="{:.3}" y1="{:.3}" x2="{:.3}" y2="{:.3}"/>"#,
+                        class, begin.x, begin.y, end.x, end.y
+                    )
+                    .unwrap();
+                }
+                let middle = point(0.5);
+                writeln!(
+                    svg,
+                    r#"<text x="{:.3}" y="{:.3}">{}</text>"#,
+                    middle.x, middle.y, number
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// Whether two segments of the flat sheet lie on the same line
+fn same_line(a: (Vec3, Vec3), b: (Vec3, Vec3)) -> bool {
+    let direction = (a.1 - a.0).normalize();
+    let off_line = |p: Vec3| {
+        let p = p - a.0;
+        (p - direction * p.dot(&direction)).norm()
+    };
+    off_line(b.0) <= ON_LINE_TOLERANCE && off_line(b.1) <= ON_LINE_TOLERANCE
+}
+
+/// Parts of the line through `from` and `to` over the faces of the flat `model`,
+/// as ranges of `t` of the points `from + (to - from) * t`
+fn across_faces(model: &Model, from: Vec3, to: Vec3) -> Vec<(f32, f32)> {
+    let cross = |a: Vec2, b: Vec2| a.x * b.y - a.y * b.x;
+    let (origin, direction) = (from.xy(), (to - from).xy());
+
+    let mut ranges: Vec<(f32, f32)> = Vec::new();
+    for &(a, b, c) in model.triangles.iter() {
+        let corners = [a, b, c].map(|i| model.vertices[i as usize].xy());
+        let area = cross(corners[1] - corners[0], corners[2] - corners[0]);
+        if area.abs() <= f32::EPSILON {
+            continue;
+        }
+        // The line is inside where it's on the inner side of all three edges
+        let (mut begin, mut end) = (f32::NEG_INFINITY, f32::INFINITY);
+        for k in 0..3 {
+            let (p, q) = (corners[k], corners[(k + 1) % 3]);
+            let edge = q - p;
+            let offset = cross(edge, origin - p) * area.signum();
+            let rate = cross(edge, direction) * area.signum();
+            if rate.abs() <= f32::EPSILON {
+                // Parallel to the edge, inside everywhere or nowhere
+                if offset < -ON_LINE_TOLERANCE * edge.norm() {
+                    end = f32::NEG_INFINITY;
+                }
+            } else if rate > 0.0 {
+                begin = begin.max(-offset / rate);
+            } else {
+                end = end.min(-offset / rate);
+            }
+        }
+        if begin < end {
+            ranges.push((begin, end));
+        }
+    }
+    if ranges.is_empty() {
+        return vec![(0.0, 1.0)];
+    }
+
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f32, f32)> = Vec::new();
+    for (begin, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if begin <= last.1 + ON_LINE_TOLERANCE / direction.norm() => {
+                last.1 = last.1.max(end)
+            }
+            _ => merged.push((begin, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has_vertex(pattern: &FoldPattern, x: f32, y: f32, tolerance: f32) -> bool {
+        let point = Vec3::new(x, y, 0.0);
+        pattern
+            .model
+            .vertices
+            .iter()
+            .any(|v| glm::distance(v, &point) <= tolerance)
+    }
+
+    #[test]
+    fn test_import_shapes() {
+        let pattern = FoldPattern::from_svg(
+            r##"<svg xmlns="http://www.w3.org/2000/svg">
+                <g transform="translate(10, 0)">
+                    <rect x="0" y="0" width="2" height="2"/>
+                    <path class="valley" d="M 0 0 L 2 2"/>
+                    <line x1="2" y1="0" x2="0" y2="2" stroke="#FF0000"/>
+                </g>
+            </svg>"##,
+            1e-4,
+        )
+        .unwrap();
+
+        assert_eq!(pattern.model.vertices.len(), 5);
+        assert!(pattern.model.triangles.is_empty());
+        assert!(has_vertex(&pattern, 10.0, 0.0, 1e-4));
+        assert!(has_vertex(&pattern, 12.0, 2.0, 1e-4));
+        assert!(has_vertex(&pattern, 11.0, 1.0, 1e-4));
+        assert_eq!(pattern.model.lines.len(), 8);
+
+        let count = |assignment| {
+            pattern
+                .assignments
+                .iter()
+                .filter(|&&a| a == assignment)
+                .count()
+        };
+        assert_eq!(count(EdgeAssignment::Border), 4);
+        assert_eq!(count(EdgeAssignment::Valley), 2);
+        assert_eq!(count(EdgeAssignment::Mountain), 2);
+    }
+
+    #[test]
+    fn test_import_bend_lines() {
+        let pattern =
+            FoldPattern::from_svg(include_str!("../../design/bend-lines.svg"), 0.5).unwrap();
+
+        // Coordinates of the right half of the sheet, as in `plane_geometry.rs`
+        for &(x, y) in [
+            (0.0, 0.0),
+            (0.0, 105.0),
+            (105.0, 105.0),
+            (13.0, 105.0),
+            (105.0, 211.064),
+            (55.733, 55.733),
+            (12.0, 12.0),
+            (6.5, 105.0),
+            (55.0, 297.0),
+            (26.3638, 26.3638),
+            (29.608, 105.0),
+            (83.073, 83.073),
+        ]
+        .iter()
+        {
+            assert!(has_vertex(&pattern, 105.0 + x, y, 0.5), "({}, {})", x, y);
+            assert!(has_vertex(&pattern, 105.0 - x, y, 0.5), "({}, {})", -x, y);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |svg: &str| FoldPattern::from_svg(svg, 0.1).unwrap_err().to_string();
+
+        assert!(error("<svg>").starts_with("invalid SVG"));
+        assert_eq!(
+            error("<svg>\n<line x1=\"a\"/></svg>"),
+            "line 2: invalid x1 \"a\""
+        );
+        assert_eq!(
+            error("<svg>\n\n<path d=\"M 0 0 Q 1 1 2 0\"/></svg>"),
+            "line 3: curved path segments can't be creases"
+        );
+        assert_eq!(
+            error("<svg><use href=\"#side\"/></svg>"),
+            "line 1: element #side not found"
+        );
+    }
+}
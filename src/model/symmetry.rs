@@ -0,0 +1,484 @@
+use crate::model::layers::Layers;
+use crate::model::timeline::Timeline;
+use crate::model::transform::fold::Fold;
+use crate::model::{AnimatedModel, Model, Transform};
+use glm::{rotation, translation, vec4_to_vec3, Mat3, Mat4, Vec3};
+use std::f32::consts::PI;
+
+/// Placement of the copies of a part of a model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    /// The part and its mirror image across the plane through `point` with the `normal`
+    Mirror { point: Vec3, normal: Vec3 },
+    /// `count` copies turned around the axis in the `direction` through the `pivot`,
+    /// evenly over the full turn
+    #[allow(dead_code)]
+    Rotation {
+        pivot: Vec3,
+        direction: Vec3,
+        count: u32,
+    },
+}
+
+impl Symmetry {
+    /// Mirror across the plane x = 0
+    pub fn mirror_x() -> Symmetry {
+        Symmetry::Mirror {
+            point: Vec3::zeros(),
+            normal: Vec3::x(),
+        }
+    }
+
+    /// Matrices moving the part to every copy, the first copy is the part itself
+    pub fn matrices(&self) -> Vec<Mat4> {
+        match *self {
+            Symmetry::Mirror { point, normal } => {
+                let normal = normal.normalize();
+                let reflection = Mat3::identity() - normal * normal.transpose() * 2.0;
+                vec![
+                    Mat4::identity(),
+                    translation(&point) * glm::mat3_to_mat4(&reflection) * translation(&-point),
+                ]
+            }
+            Symmetry::Rotation {
+                pivot,
+                direction,
+                count,
+            } => (0..count)
+                .map(|k| {
+                    let angle = PI * 2.0 * k as f32 / count as f32;
+                    translation(&pivot)
+                        * rotation(angle, &direction.normalize())
+                        * translation(&-pivot)
+                })
+                .collect(),
+        }
+    }
+}
+
+fn is_mirroring(matrix: &Mat4) -> bool {
+    glm::mat4_to_mat3(matrix).determinant() < 0.0
+}
+
+/// Vertex `i` of the part in copy `k` has the index `map[k * count + i]` in the model
+/// of all copies, for the part with `count` vertices
+fn copy_map(map: &[u32], count: usize, copy: usize) -> &[u32] {
+    &map[copy * count..(copy + 1) * count]
+}
+
+/// Copies of the part merged one after another, without joining them.
+/// Triangles of mirrored copies are reversed, so their normals point to the same side
+/// of the paper as in the part.
+fn place(part: &Model, matrices: &[Mat4]) -> Model {
+    let empty = Model {
+        vertices: vec![],
+        triangles: vec![],
+        lines: vec![],
+    };
+    matrices
+        .iter()
+        .map(|matrix| {
+            let copy = part.clone().transform(*matrix);
+            if is_mirroring(matrix) {
+                Model {
+                    triangles: copy.triangles.iter().map(|&(a, b, c)| (a, c, b)).collect(),
+                    ..copy
+                }
+            } else {
+                copy
+            }
+        })
+        .fold(empty, |all, copy| {
+            all.merge(copy).expect("copies fit into u32 indices")
+        })
+}
+
+/// Animated part of a model shown with all its symmetric copies. Vertices where the copies
+/// meet in the first state are shared, so the copies stay joined along the seams.
+/// The first copy keeps the indices of the part, the vertices of the other copies follow.
+/// The animation of the part must keep its vertex count, the seams are found once.
+pub struct Symmetric<M: AnimatedModel> {
+    model: M,
+    matrices: Vec<Mat4>,
+    /// Vertex count of the part
+    count: usize,
+    /// Index of vertex `i` of copy `k`, at `k * count + i`, in the model of all copies
+    map: Vec<u32>,
+}
+
+impl<M: AnimatedModel> Symmetric<M> {
+    /// Copies of the `model` placed by the `symmetry`, joined where their vertices
+    /// are closer than `tolerance` in the first state
+    pub fn new(model: M, symmetry: Symmetry, tolerance: f32) -> Self {
+        let matrices = symmetry.matrices();
+        let part = model.get_model(0.0);
+        let count = part.vertices.len();
+        let copies = place(&part, &matrices);
+
+        // Welding can join vertices of the part itself, only joins between copies are kept
+        let welded = copies.weld_map(tolerance);
+        let mut first: Vec<Option<usize>> = vec![None; copies.vertices.len()];
+        let mut map: Vec<u32> = Vec::with_capacity(welded.len());
+        let mut next = count as u32;
+        for (i, &w) in welded.iter().enumerate() {
+            let joined = *first[w as usize].get_or_insert(i);
+            let index = if joined / count < i / count {
+                map[joined]
+            } else if i < count {
+                i as u32
+            } else {
+                next += 1;
+                next - 1
+            };
+            map.push(index);
+        }
+
+        Self {
+            model,
+            matrices,
+            count,
+            map,
+        }
+    }
+
+    /// The animated part
+    #[allow(dead_code)]
+    pub fn part(&self) -> &M {
+        &self.model
+    }
+
+    /// State of the part at `time`, with the vertices the copies are made for
+    fn part_at(&self, time: f32) -> Model {
+        let part = self.model.get_model(time);
+        assert_eq!(
+            part.vertices.len(),
+            self.count,
+            "animation of the part changes its vertex count"
+        );
+        part
+    }
+
+    /// Copies at the end of the animation, to transform them in the following steps
+    pub fn copies(&self) -> Copies {
+        Copies {
+            matrices: self.matrices.clone(),
+            map: self.map.clone(),
+            count: self.count,
+            triangles: self.part_at(self.model.duration()).triangles,
+        }
+    }
+}
+
+impl<M: AnimatedModel> AnimatedModel for Symmetric<M> {
+    fn duration(&self) -> f32 {
+        self.model.duration()
+    }
+
+    fn get_model(&self, time: f32) -> Model {
+        place(&self.part_at(time), &self.matrices).reindex(&self.map)
+    }
+
+    /// Folds of the part repeated on every copy, with the indices of the last state
+    fn fold_steps(&self) -> Vec<Vec<Fold>> {
+        let copies = &self.copies();
+        self.model
+            .fold_steps()
+            .iter()
+            .map(|folds| {
+                (0..self.matrices.len())
+                    .flat_map(|copy| folds.iter().map(move |fold| copies.fold(copy, fold)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Layers of the part on every copy. Mirroring keeps the order along the normals,
+    /// but faces of different copies are never stacked together.
+    fn layers(&self, time: f32) -> Layers {
+        let faces = self.model.get_model(time).triangles.len();
+        self.model.layers(time).repeat(faces, self.matrices.len())
+    }
+
+    fn step_at(&self, time: f32) -> Option<usize> {
+        self.model.step_at(time)
+    }
+
+    fn timeline(&self) -> Timeline {
+        self.model.timeline()
+    }
+}
+
+/// Copies of a symmetric model, to apply transforms of the part to some of them
+#[derive(Debug, Clone)]
+pub struct Copies {
+    matrices: Vec<Mat4>,
+    map: Vec<u32>,
+    /// Vertex count of the part
+    count: usize,
+    triangles: Vec<(u32, u32, u32)>,
+}
+
+impl Copies {
+    pub fn len(&self) -> usize {
+        self.matrices.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.matrices.is_empty()
+    }
+
+    /// Index of the part `vertex` of the `copy` in the model of all copies
+    #[allow(dead_code)]
+    pub fn index(&self, copy: usize, vertex: u32) -> u32 {
+        copy_map(&self.map, self.count, copy)[vertex as usize]
+    }
+
+    /// The `copy` taken out of the model of all copies and moved back to the place of the part,
+    /// so transforms of the part apply to it
+    #[allow(dead_code)]
+    pub fn part(&self, model: &Model, copy: usize) -> Model {
+        let inverse = glm::inverse(&self.matrices[copy]);
+        Model {
+            vertices: copy_map(&self.map, self.count, copy)
+                .iter()
+                .map(|&i| vec4_to_vec3(&(inverse * model.vertices[i as usize].push(1.0))))
+                .collect(),
+            triangles: self.triangles.clone(),
+            lines: vec![],
+        }
+    }
+
+    /// The fold of the part done on the `copy`, mirrored copies fold the other way
+    fn fold(&self, copy: usize, fold: &Fold) -> Fold {
+        let fold = fold.reindex(copy_map(&self.map, self.count, copy));
+        if is_mirroring(&self.matrices[copy]) {
+            fold.reversed()
+        } else {
+            fold
+        }
+    }
+
+    /// The `transform` of the part done on every copy
+    #[allow(dead_code)]
+    pub fn all<T: Transform>(&self, transform: T) -> OnCopies<T> {
+        OnCopies {
+            copies: self.clone(),
+            selected: (0..self.len()).collect(),
+            transform,
+        }
+    }
+
+    /// The `transform` of the part done on the `copy` only
+    #[allow(dead_code)]
+    pub fn one<T: Transform>(&self, copy: usize, transform: T) -> OnCopies<T> {
+        OnCopies {
+            copies: self.clone(),
+            selected: vec![copy],
+            transform,
+        }
+    }
+}
+
+/// Transform of the part applied to some copies of a symmetric model.
+/// The transform must keep the vertices and triangles of the part, as the copies can't grow.
+pub struct OnCopies<T: Transform> {
+    copies: Copies,
+    selected: Vec<usize>,
+    transform: T,
+}
+
+impl<T: Transform> Transform for OnCopies<T> {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        // All copies are taken before any of them moves, so shared vertices move once
+        let parts: Vec<Model> = self
+            .selected
+            .iter()
+            .map(|&copy| self.transform.apply(self.copies.part(&model, copy), t))
+            .collect();
+        for part in parts.iter() {
+            assert_eq!(
+                part.vertices.len(),
+                self.copies.count,
+                "transform adds vertices"
+            );
+            assert_eq!(
+                part.triangles, self.copies.triangles,
+                "transform changes faces"
+            );
+        }
+
+        let mut model = model;
+        let count = self.copies.count;
+        for (&copy, part) in self.selected.iter().zip(parts) {
+            let matrix = self.copies.matrices[copy];
+            let map = copy_map(&self.copies.map, count, copy);
+            for (vertex, &i) in part.vertices.iter().zip(map.iter()) {
+                model.vertices[i as usize] = vec4_to_vec3(&(matrix * vertex.push(1.0)));
+            }
+            for &(a, b, alpha, crease) in part.lines.iter() {
+                if (a as usize) < count && (b as usize) < count {
+                    model
+                        .lines
+                        .push((map[a as usize], map[b as usize], alpha, crease));
+                }
+            }
+        }
+        model
+    }
+
+    fn folds(&self, model: &Model) -> Vec<Fold> {
+        self.selected
+            .iter()
+            .flat_map(|&copy| {
+                self.transform
+                    .folds(&self.copies.part(model, copy))
+                    .into_iter()
+                    .map(move |fold| self.copies.fold(copy, &fold))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_set::BitSet;
+    use crate::model::transform::fold::FoldSide;
+
+    /// Square from x = 0 to x = 1, the left side is on the symmetry plane
+    fn half() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 3), (1, 2, 3)],
+            lines: vec![],
+        }
+    }
+
+    /// Fold of the corner 2 of the half over the line from 1 to 3
+    fn corner(angle: f32) -> Fold {
+        Fold::new((1, 3), BitSet::with_bits(&[2]), angle)
+    }
+
+    fn mirrored(v: &Vec3) -> Vec3 {
+        Vec3::new(-v.x, v.y, v.z)
+    }
+
+    fn close(a: &Vec3, b: &Vec3) -> bool {
+        (a - b).norm() < 1e-5
+    }
+
+    #[test]
+    fn test_mirror_shares_seam() {
+        let model = half().symmetric(Symmetry::mirror_x(), 1e-5).get_model(0.0);
+        assert_eq!(model.vertices.len(), 6);
+        assert_eq!(&model.vertices[..4], &half().vertices[..]);
+        assert_eq!(model.vertices[4], Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(
+            model.triangles,
+            vec![(0, 1, 3), (1, 2, 3), (0, 3, 4), (4, 3, 5)]
+        );
+        assert!(model.validate().is_valid());
+    }
+
+    #[test]
+    fn test_rotation_shares_seams() {
+        let wedge = Model {
+            vertices: vec![
+                Vec3::zeros(),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(-0.5, 0.75f32.sqrt(), 0.0),
+            ],
+            triangles: vec![(0, 1, 2)],
+            lines: vec![],
+        };
+        let symmetry = Symmetry::Rotation {
+            pivot: Vec3::zeros(),
+            direction: Vec3::z(),
+            count: 3,
+        };
+        let model = wedge.symmetric(symmetry, 1e-5).get_model(0.0);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.triangles, vec![(0, 1, 2), (0, 2, 3), (0, 3, 1)]);
+    }
+
+    #[test]
+    fn test_part_folds_on_every_copy() {
+        let model = half()
+            .animate(1.0, corner(-PI * 0.5))
+            .symmetric(Symmetry::mirror_x(), 1e-5);
+        let folded = model.get_model(1.0);
+        assert!(folded.vertices[2].z > 0.5);
+        assert!(close(&folded.vertices[5], &mirrored(&folded.vertices[2])));
+
+        let steps = model.fold_steps();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0][0], corner(-PI * 0.5));
+        assert_eq!(
+            steps[0][1],
+            Fold::new((4, 3), BitSet::with_bits(&[5]), PI * 0.5)
+        );
+        // The fold of the mirrored copy done on the model of both copies
+        let copy = steps[0][1].apply(model.get_model(0.0), 1.0);
+        assert!(close(&copy.vertices[5], &folded.vertices[5]));
+    }
+
+    #[test]
+    #[should_panic(expected = "vertex count")]
+    fn test_part_keeps_vertex_count() {
+        // The bend splits the faces near the crease in the second step only
+        let model = half()
+            .animate(1.0, corner(0.0))
+            .animate(1.0, corner(-PI * 0.5).bend(0.2))
+            .symmetric(Symmetry::mirror_x(), 1e-5);
+        model.get_model(1.5);
+    }
+
+    #[test]
+    fn test_fold_one_copy() {
+        let model = half().symmetric(Symmetry::mirror_x(), 1e-5);
+        let copies = model.copies();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies.index(1, 0), 0);
+        assert_eq!(copies.index(1, 2), 5);
+
+        let part = copies.part(&model.get_model(0.0), 1);
+        assert_eq!(part.vertices, half().vertices);
+        let fold = Fold::with_side(&part, (1, 3), FoldSide::Component(2), -PI * 0.5).unwrap();
+        let model = model.animate(1.0, copies.one(1, fold));
+        let twisted = model.get_model(1.0);
+        assert_eq!(&twisted.vertices[..4], &half().vertices[..]);
+        assert!(close(
+            &twisted.vertices[5],
+            &Vec3::new(-0.5, 0.5, 0.5f32.sqrt())
+        ));
+        assert_eq!(
+            model.fold_steps()[0],
+            vec![Fold::new((4, 3), BitSet::with_bits(&[5]), PI * 0.5)]
+        );
+    }
+
+    #[test]
+    fn test_fold_all_copies() {
+        let model = half().symmetric(Symmetry::mirror_x(), 1e-5);
+        let copies = model.copies();
+        let model = model.animate(1.0, copies.all(corner(-PI * 0.5)));
+        let expected = half()
+            .animate(1.0, corner(-PI * 0.5))
+            .symmetric(Symmetry::mirror_x(), 1e-5)
+            .get_model(1.0);
+        for (a, b) in model
+            .get_model(1.0)
+            .vertices
+            .iter()
+            .zip(expected.vertices.iter())
+        {
+            assert!(close(a, b), "{} != {}", a, b);
+        }
+    }
+}
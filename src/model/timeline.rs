@@ -0,0 +1,252 @@
+/// One step of an animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Index of the step in `AnimatedModel::fold_steps`, steps played backwards
+    /// keep the index of the step they undo
+    pub index: usize,
+    pub label: Option<String>,
+    /// Time the step begins, before its delay
+    pub begin: f32,
+    /// Time the paper moves, from the end of the delay to the start of the hold
+    pub motion: (f32, f32),
+    /// Time the step ends, after its hold
+    pub end: f32,
+}
+
+impl Step {
+    /// Part of the motion done at `time`
+    pub fn progress(&self, time: f32) -> f32 {
+        let (start, end) = self.motion;
+        if end > start {
+            ((time - start) / (end - start)).clamp(0.0, 1.0)
+        } else if time >= start {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Step playing at some time and the part of its motion done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepAt {
+    /// Index of the step, see `Step::index`
+    pub index: usize,
+    /// Position of the step in `Timeline::steps`
+    pub position: usize,
+    pub progress: f32,
+}
+
+/// Steps of an animation in the order of time, every step begins where the previous one ends
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Timeline {
+    steps: Vec<Step>,
+}
+
+impl Timeline {
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn push(&mut self, step: Step) {
+        self.steps.push(step);
+    }
+
+    /// The step playing at `time`, the last one begun by then, none before the first step
+    pub fn at(&self, time: f32) -> Option<StepAt> {
+        let begun = count_until(&self.steps, |step| step.begin <= time);
+        let position = begun.checked_sub(1)?;
+        let step = &self.steps[position];
+        Some(StepAt {
+            index: step.index,
+            position,
+            progress: step.progress(time),
+        })
+    }
+
+    /// The same steps played backwards in an animation of `duration`
+    pub fn reversed(&self, duration: f32) -> Timeline {
+        Timeline {
+            steps: self
+                .steps
+                .iter()
+                .rev()
+                .map(|step| Step {
+                    index: step.index,
+                    label: step.label.clone(),
+                    begin: duration - step.end,
+                    motion: (duration - step.motion.1, duration - step.motion.0),
+                    end: duration - step.begin,
+                })
+                .collect(),
+        }
+    }
+
+    /// Times multiplied by `factor`, like to get the steps in parts of the whole animation
+    pub fn scaled(&self, factor: f32) -> Timeline {
+        Timeline {
+            steps: self
+                .steps
+                .iter()
+                .map(|step| Step {
+                    index: step.index,
+                    label: step.label.clone(),
+                    begin: step.begin * factor,
+                    motion: (step.motion.0 * factor, step.motion.1 * factor),
+                    end: step.end * factor,
+                })
+                .collect(),
+        }
+    }
+
+    /// Times moved later by `offset`, like to play the steps after another animation
+    pub fn shifted(&self, offset: f32) -> Timeline {
+        Timeline {
+            steps: self
+                .steps
+                .iter()
+                .map(|step| Step {
+                    begin: step.begin + offset,
+                    motion: (step.motion.0 + offset, step.motion.1 + offset),
+                    end: step.end + offset,
+                    ..step.clone()
+                })
+                .collect(),
+        }
+    }
+
+    /// This timeline played with a `pause` after the motion of every step,
+    /// and squeezed to the same duration
+    pub fn paused(&self, pause: f32) -> Paused {
+        Paused {
+            motion_ends: self.steps.iter().map(|step| step.motion.1).collect(),
+            // Pause `i` is from `motion.1 + i * pause` to `motion.1 + (i + 1) * pause`
+            ends: self
+                .steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| step.motion.1 + (i + 1) as f32 * pause)
+                .collect(),
+            pause,
+            duration: self.steps.last().map_or(0.0, |step| step.end),
+        }
+    }
+}
+
+/// Timeline played with pauses, see `Timeline::paused`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paused {
+    motion_ends: Vec<f32>,
+    /// Ends of the pauses in the played time before it's squeezed
+    ends: Vec<f32>,
+    pause: f32,
+    duration: f32,
+}
+
+impl Paused {
+    /// Time of the timeline at `time` of the played one
+    pub fn time(&self, time: f32) -> f32 {
+        if self.duration <= 0.0 {
+            return time;
+        }
+        let count = self.ends.len() as f32;
+        let time =
+            time.clamp(0.0, self.duration) * (self.duration + self.pause * count) / self.duration;
+        let passed = count_until(&self.ends, |&end| end <= time);
+        match self.motion_ends.get(passed) {
+            Some(&end) if time > end + passed as f32 * self.pause => end,
+            _ => (time - passed as f32 * self.pause).clamp(0.0, self.duration),
+        }
+    }
+}
+
+/// Length of the prefix of `items` where `predicate` holds, it must hold for a prefix only
+fn count_until<T>(items: &[T], predicate: impl Fn(&T) -> bool) -> usize {
+    let (mut low, mut high) = (0, items.len());
+    while low < high {
+        let middle = (low + high) / 2;
+        if predicate(&items[middle]) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(index: usize, label: &str, begin: f32, motion: (f32, f32), end: f32) -> Step {
+        Step {
+            index,
+            label: Some(label.to_string()),
+            begin,
+            motion,
+            end,
+        }
+    }
+
+    /// Fold with a delay, then a lift that holds the end
+    fn timeline() -> Timeline {
+        let mut timeline = Timeline::default();
+        timeline.push(step(0, "fold", 0.0, (0.5, 1.5), 1.5));
+        timeline.push(step(1, "lift", 1.5, (1.5, 2.5), 3.0));
+        timeline
+    }
+
+    #[test]
+    fn test_step_at() {
+        let timeline = timeline();
+        let at = |time: f32| timeline.at(time).map(|at| (at.index, at.progress));
+        assert_eq!(at(-1.0), None);
+        assert_eq!(at(0.25), Some((0, 0.0)));
+        assert_eq!(at(1.0), Some((0, 0.5)));
+        assert_eq!(at(1.5), Some((1, 0.0)));
+        assert_eq!(at(2.75), Some((1, 1.0)));
+        assert_eq!(at(10.0), Some((1, 1.0)));
+        assert_eq!(Timeline::default().at(1.0), None);
+    }
+
+    #[test]
+    fn test_reversed() {
+        let reversed = timeline().reversed(3.0);
+        assert_eq!(reversed.steps()[0], step(1, "lift", 0.0, (0.5, 1.5), 1.5));
+        assert_eq!(reversed.steps()[1], step(0, "fold", 1.5, (1.5, 2.5), 3.0));
+        assert_eq!(
+            reversed.at(0.5).map(|at| (at.index, at.position)),
+            Some((1, 0))
+        );
+        assert_eq!(reversed.reversed(3.0), timeline());
+    }
+
+    #[test]
+    fn test_scaled() {
+        let scaled = timeline().scaled(0.5);
+        assert_eq!(scaled.steps()[1], step(1, "lift", 0.75, (0.75, 1.25), 1.5));
+    }
+
+    #[test]
+    fn test_shifted() {
+        let shifted = timeline().shifted(2.0);
+        assert_eq!(shifted.steps()[0], step(0, "fold", 2.0, (2.5, 3.5), 3.5));
+        assert_eq!(shifted.at(4.0).map(|at| at.index), Some(1));
+    }
+
+    #[test]
+    fn test_paused() {
+        // Pauses of 0.75 after both steps take a third of the played time
+        let paused = timeline().paused(0.75);
+        let paused = |time: f32| paused.time(time);
+        assert_eq!(paused(0.0), 0.0);
+        assert_eq!(paused(0.5), 0.75);
+        assert_eq!(paused(1.0), 1.5);
+        assert_eq!(paused(1.25), 1.5);
+        assert_eq!(paused(1.5), 1.5);
+        assert_eq!(paused(2.0), 2.25);
+        assert_eq!(paused(2.5), 2.5);
+        assert_eq!(paused(3.0), 3.0);
+        assert_eq!(Timeline::default().paused(1.0).time(0.5), 0.5);
+    }
+}
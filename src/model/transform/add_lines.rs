@@ -1,19 +1,25 @@
-use crate::model::{Model, Transform};
+use crate::model::{Crease, Model, Transform};
 
 pub struct AddLines {
-    lines: Vec<(u8, u8)>,
+    lines: Vec<(u32, u32)>,
+    crease: Crease,
 }
 
 impl AddLines {
-    pub fn new(lines: Vec<(u8, u8)>) -> Self {
-        Self { lines }
+    pub fn new(lines: Vec<(u32, u32)>, crease: Crease) -> Self {
+        Self { lines, crease }
     }
 }
 
 impl Transform for AddLines {
     fn apply(&self, model: Model, t: f32) -> Model {
         let mut lines = model.lines;
-        lines.extend(self.lines.iter().copied().map(|(a, b)| (a, b, t)));
+        lines.extend(
+            self.lines
+                .iter()
+                .copied()
+                .map(|(a, b)| (a, b, t, self.crease)),
+        );
         Model { lines, ..model }
     }
 }
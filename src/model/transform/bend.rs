@@ -0,0 +1,250 @@
+use crate::bit_set::BitSet;
+use crate::model::adjacency::Adjacency;
+use crate::model::transform::fold::Fold;
+use crate::model::transform::transform::Transform;
+use crate::model::Model;
+use glm::Vec3;
+use std::cell::RefCell;
+
+/// Segments of the rolled part of the paper, the edges near the crease are split
+/// until they are shorter than one segment
+const SEGMENTS: f32 = 8.0;
+/// Vertices closer to the crease line than this part of its length are on the line
+const ON_LINE_TOLERANCE: f32 = 1e-4;
+
+/// Fold that rolls the paper around a cylinder near the crease instead of a sharp hinge.
+/// The radius shrinks as the fold goes on, so the end state is the same as of `Fold`.
+/// Edges near the crease are split first, see `Model::subdivide_near`, so the rolled part
+/// has vertices to curve. The new vertices are added after the existing ones
+/// in every state of the step, so the next steps get the subdivided model.
+///
+/// The subdivision is kept for the last input model, so the states of a step
+/// that starts from the same model split the edges once.
+pub struct Bend {
+    line: (u32, u32),
+    points: BitSet,
+    angle: f32,
+    radius: f32,
+    /// The last input model with its subdivision and the moving vertices of it
+    subdivided: RefCell<Option<(Model, Model, BitSet)>>,
+}
+
+impl Bend {
+    pub fn new(line: (u32, u32), points: BitSet, angle: f32, radius: f32) -> Self {
+        Self {
+            line,
+            points,
+            angle,
+            radius,
+            subdivided: RefCell::new(None),
+        }
+    }
+
+    /// `subdivide` of the `model`, reused while the input stays the same
+    fn subdivided(&self, model: Model) -> (Model, BitSet) {
+        let mut cache = self.subdivided.borrow_mut();
+        if let Some((input, subdivided, points)) = cache.as_ref() {
+            if *input == model {
+                return (subdivided.clone(), points.clone());
+            }
+        }
+        let (subdivided, points) = self.subdivide(model.clone());
+        *cache = Some((model, subdivided.clone(), points.clone()));
+        (subdivided, points)
+    }
+
+    /// The model with the edges near the crease split and the moving vertices
+    /// with the new ones on the moving side
+    fn subdivide(&self, model: Model) -> (Model, BitSet) {
+        let p0 = model.vertices[self.line.0 as usize];
+        let p1 = model.vertices[self.line.1 as usize];
+        let arc = self.radius * self.angle.abs();
+        if arc <= 0.0 {
+            return (model, self.points.clone());
+        }
+        let count = model.vertices.len();
+        let model = model.subdivide_near(p0, p1, arc, arc / SEGMENTS);
+
+        let axis = (p1 - p0).normalize();
+        let tolerance = (p1 - p0).norm() * ON_LINE_TOLERANCE;
+        let on_line = |v: &Vec3| {
+            let offset = v - p0;
+            (offset - axis * offset.dot(&axis)).norm() <= tolerance
+        };
+        // Faces don't cross the crease, so new vertices of the faces with a moving vertex
+        // move too, unless they are on the crease line
+        let adjacency = Adjacency::new(&model);
+        let mut points = self.points.clone();
+        let mut queue: Vec<u32> = points.iter().collect();
+        while let Some(vertex) = queue.pop() {
+            for face in adjacency.vertex_faces(vertex) {
+                let (a, b, c) = model.triangles[face];
+                for &v in [a, b, c].iter() {
+                    let new = v as usize >= count;
+                    if new && !points.has(v) && !on_line(&model.vertices[v as usize]) {
+                        points.insert(v);
+                        queue.push(v);
+                    }
+                }
+            }
+        }
+        (model, points)
+    }
+}
+
+impl Transform for Bend {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        let (model, points) = self.subdivided(model);
+        let mut vertices = model.vertices;
+        let p0 = vertices[self.line.0 as usize];
+        let axis = (vertices[self.line.1 as usize] - p0).normalize();
+        let angle = self.angle * t;
+        let radius = self.radius * (1.0 - t);
+        // Length of the paper rolled around the cylinder
+        let arc = radius * angle.abs();
+
+        for (i, vertex) in vertices.iter_mut().enumerate() {
+            if !points.has(i as u32) {
+                continue;
+            }
+            let offset = *vertex - p0;
+            let along = axis * offset.dot(&axis);
+            let across = offset - along;
+            let distance = across.norm();
+            if distance == 0.0 {
+                continue;
+            }
+            let u = across / distance;
+            let w = axis.cross(&u);
+
+            let rolled = distance.min(arc);
+            // Position on the cylinder along the sheet and away from it
+            let (forward, lift) = if radius > 0.0 {
+                let phi = rolled / radius;
+                (radius * phi.sin(), radius * (1.0 - phi.cos()))
+            } else {
+                (0.0, 0.0)
+            };
+            let bent = u * forward + w * (lift * angle.signum());
+            let flat = (u * angle.cos() + w * angle.sin()) * (distance - rolled);
+            *vertex = p0 + along + bent + flat;
+        }
+
+        Model { vertices, ..model }
+    }
+
+    fn folds(&self, _model: &Model) -> Vec<Fold> {
+        vec![Fold::new(self.line, self.points.clone(), self.angle)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Strip along x with the crease at x = 0, the right part moves
+    fn strip() -> Model {
+        let mut vertices = vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        for i in 1..=10 {
+            vertices.push(Vec3::new(i as f32 * 0.1, 0.0, 0.0));
+        }
+        Model {
+            vertices,
+            triangles: vec![],
+            lines: vec![],
+        }
+    }
+
+    fn bend() -> Bend {
+        Bend::new((0, 1), (2..12).collect(), PI, 0.2)
+    }
+
+    #[test]
+    fn test_ends_as_fold() {
+        let fold = Fold::new((0, 1), (2..12).collect(), PI);
+        let bent = bend().apply(strip(), 1.0);
+        let folded = fold.apply(strip(), 1.0);
+        for (a, b) in bent.vertices.iter().zip(folded.vertices.iter()) {
+            assert!((a - b).norm() < 1e-5, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_keeps_paper_length() {
+        let model = bend().apply(strip(), 0.5);
+        // Vertices past the rolled part stay on a straight line of the same length
+        let flat = model.vertices[11] - model.vertices[3];
+        assert!((flat.norm() - 0.8).abs() < 1e-5);
+        // The end of the strip is turned by the half of the angle
+        assert!((flat.normalize() - Vec3::new(0.0, 0.0, -1.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_rolled_part_is_on_cylinder() {
+        let model = bend().apply(strip(), 0.5);
+        // Radius is 0.1 in the middle of the fold and the rolled part is 0.157 long
+        let centre = Vec3::new(0.0, 0.0, -0.1);
+        let distance = (model.vertices[2] - centre).xz().norm();
+        assert!((distance - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_static_side() {
+        let mut model = strip();
+        model.vertices.push(Vec3::new(-0.5, 0.0, 0.0));
+        let bent = bend().apply(model, 0.5);
+        assert_eq!(bent.vertices[12], Vec3::new(-0.5, 0.0, 0.0));
+        assert_eq!(bent.vertices[0], Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_subdivides_near_crease() {
+        // Square folded over the diagonal from 0 to 2, the corner 3 moves
+        let square = Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3)],
+            lines: vec![],
+        };
+        let bend = Bend::new((0, 2), BitSet::with_bits(&[3]), PI * 0.5, 0.2);
+        let model = bend.apply(square.clone(), 0.5);
+        assert!(model.vertices.len() > 10);
+        assert!(model.validate().is_valid(), "{}", model.validate());
+        let flat = bend.apply(square.clone(), 0.0);
+        for (vertex, flat) in model.vertices.iter().zip(flat.vertices.iter()).skip(4) {
+            // New vertices of the static half stay, the ones of the moving half are lifted
+            if flat.y < flat.x - 1e-5 {
+                assert_eq!(vertex.z, 0.0, "{}", flat);
+            } else if flat.y > flat.x + 1e-5 {
+                assert!(vertex.z > 0.0, "{}", flat);
+            }
+        }
+
+        let folded =
+            Fold::new((0, 2), BitSet::with_bits(&[3]), PI * 0.5).apply(square.clone(), 1.0);
+        let bent = bend.apply(square, 1.0);
+        for (a, b) in bent.vertices.iter().zip(folded.vertices.iter()) {
+            assert!((a - b).norm() < 1e-5, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_subdivision_follows_input() {
+        let bend = bend();
+        let short = bend.apply(strip(), 0.5);
+        assert_eq!(bend.apply(strip(), 0.5).vertices, short.vertices);
+        // Another input is subdivided again instead of reusing the last one
+        let mut long = strip();
+        for vertex in long.vertices.iter_mut() {
+            vertex.x *= 2.0;
+        }
+        let expected = self::bend().apply(long.clone(), 0.5);
+        assert_eq!(bend.apply(long, 0.5).vertices, expected.vertices);
+        assert_eq!(bend.apply(strip(), 0.5).vertices, short.vertices);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::bit_set::BitSet;
+use crate::model::transform::fold::Fold;
+use crate::model::{Crease, Model, Transform};
+use glm::{rotation, translation, vec4_to_vec3, Mat4, Vec2, Vec3};
+use std::collections::HashMap;
+
+/// Vertices closer to the cut plane than this are on the plane
+const ON_PLANE_TOLERANCE: f32 = 1e-5;
+
+/// Fold along any line in space. Triangles crossing the cut plane are split,
+/// new vertices are appended after the existing ones, and the part of the model
+/// on the `normal` side of the plane is rotated around the axis.
+pub struct CutFold {
+    point: Vec3,
+    axis: Vec3,
+    normal: Vec3,
+    angle: f32,
+}
+
+impl CutFold {
+    /// Fold around the axis going through `point`, the cut plane contains the axis
+    /// and `normal` is made perpendicular to it
+    #[allow(dead_code)]
+    pub fn new(point: Vec3, axis: Vec3, normal: Vec3, angle: f32) -> Self {
+        let axis = axis.normalize();
+        let normal = (normal - axis * normal.dot(&axis)).normalize();
+        Self {
+            point,
+            axis,
+            normal,
+            angle,
+        }
+    }
+
+    /// Fold of a flat sheet in the z = 0 plane, the part on the left of the line moves
+    #[allow(dead_code)]
+    pub fn line(from: Vec2, to: Vec2, angle: f32) -> Self {
+        let direction = to - from;
+        Self::new(
+            Vec3::new(from.x, from.y, 0.0),
+            Vec3::new(direction.x, direction.y, 0.0),
+            Vec3::new(-direction.y, direction.x, 0.0),
+            angle,
+        )
+    }
+
+    fn distance(&self, vertex: &Vec3) -> f32 {
+        (vertex - self.point).dot(&self.normal)
+    }
+
+    fn side(&self, vertex: &Vec3) -> i8 {
+        let distance = self.distance(vertex);
+        if distance > ON_PLANE_TOLERANCE {
+            1
+        } else if distance < -ON_PLANE_TOLERANCE {
+            -1
+        } else {
+            0
+        }
+    }
+
+    /// Split triangles and lines crossing the plane, crease lines are added along the cut
+    fn cut(&self, model: Model, t: f32) -> Model {
+        let mut vertices = model.vertices;
+        let sides: Vec<i8> = vertices.iter().map(|v| self.side(v)).collect();
+        let mut cuts: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut cut_edge = |a: u32, b: u32, vertices: &mut Vec<Vec3>| {
+            *cuts.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let (p, q) = (vertices[a as usize], vertices[b as usize]);
+                let (dp, dq) = (self.distance(&p), self.distance(&q));
+                vertices.push(p + (q - p) * (dp / (dp - dq)));
+                vertices.len() as u32 - 1
+            })
+        };
+        let side = |i: u32| sides.get(i as usize).copied().unwrap_or(0);
+
+        let mut triangles = Vec::with_capacity(model.triangles.len());
+        let mut creases: HashMap<(u32, u32), Crease> = HashMap::new();
+        for &(a, b, c) in model.triangles.iter() {
+            let corners = [a, b, c];
+            if !corners.iter().any(|&i| side(i) > 0) || !corners.iter().any(|&i| side(i) < 0) {
+                triangles.push((a, b, c));
+                continue;
+            }
+
+            // Polygons on both sides of the plane, in the triangle winding order
+            let mut positive = Vec::with_capacity(4);
+            let mut negative = Vec::with_capacity(4);
+            let mut on_plane = Vec::with_capacity(2);
+            for k in 0..3 {
+                let (p, q) = (corners[k], corners[(k + 1) % 3]);
+                if side(p) >= 0 {
+                    positive.push(p);
+                }
+                if side(p) <= 0 {
+                    negative.push(p);
+                }
+                if side(p) == 0 {
+                    on_plane.push(p);
+                }
+                if side(p) * side(q) < 0 {
+                    let m = cut_edge(p, q, &mut vertices);
+                    positive.push(m);
+                    negative.push(m);
+                    on_plane.push(m);
+                }
+            }
+            for polygon in [positive, negative].iter() {
+                for k in 1..polygon.len() - 1 {
+                    triangles.push((polygon[0], polygon[k], polygon[k + 1]));
+                }
+            }
+            if let [p, q] = on_plane[..] {
+                // The moving part goes to the front of the face in a valley fold
+                let v = |i: u32| vertices[i as usize];
+                let front = (v(b) - v(a)).cross(&(v(c) - v(a)));
+                let lift = self.axis.cross(&self.normal).dot(&front) * self.angle;
+                let crease = if lift > 0.0 {
+                    Crease::Valley
+                } else {
+                    Crease::Mountain
+                };
+                creases.insert((p.min(q), p.max(q)), crease);
+            }
+        }
+
+        let mut lines = Vec::with_capacity(model.lines.len() + creases.len());
+        for &(a, b, alpha, crease) in model.lines.iter() {
+            if side(a) * side(b) < 0 {
+                let m = cut_edge(a, b, &mut vertices);
+                lines.push((a, m, alpha, crease));
+                lines.push((m, b, alpha, crease));
+            } else {
+                lines.push((a, b, alpha, crease));
+            }
+        }
+        let mut creases: Vec<((u32, u32), Crease)> = creases.into_iter().collect();
+        creases.sort_unstable_by_key(|&(line, _)| line);
+        lines.extend(
+            creases
+                .into_iter()
+                .map(|((a, b), crease)| (a, b, t, crease)),
+        );
+
+        Model {
+            vertices,
+            triangles,
+            lines,
+        }
+    }
+}
+
+impl Transform for CutFold {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        let mut model = self.cut(model, t);
+        let transformation: Mat4 = translation(&self.point)
+            * rotation(self.angle * t, &self.axis)
+            * translation(&(-self.point));
+        for vertex in model.vertices.iter_mut() {
+            if self.side(vertex) > 0 {
+                *vertex = vec4_to_vec3(&(transformation * vertex.push(1.0)));
+            }
+        }
+        model
+    }
+
+    /// The fold on the cut model, hinged on the two cut vertices farthest apart
+    /// along the axis. A plane that misses the model does no fold.
+    fn folds(&self, model: &Model) -> Vec<Fold> {
+        let model = self.cut(model.clone(), 1.0);
+        let along = |i: u32| (model.vertices[i as usize] - self.point).dot(&self.axis);
+        let mut hinge: Vec<u32> = model
+            .triangles
+            .iter()
+            .flat_map(|&(a, b, c)| vec![a, b, c])
+            .filter(|&i| self.side(&model.vertices[i as usize]) == 0)
+            .collect();
+        hinge.sort_unstable_by(|&a, &b| along(a).total_cmp(&along(b)));
+        let line = match (hinge.first(), hinge.last()) {
+            (Some(&a), Some(&b)) if along(b) - along(a) > ON_PLANE_TOLERANCE => (a, b),
+            _ => return vec![],
+        };
+        let points: BitSet = (0..model.vertices.len() as u32)
+            .filter(|&i| self.side(&model.vertices[i as usize]) > 0)
+            .collect();
+        vec![Fold::new(line, points, self.angle)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn square() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(2.0, 2.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3)],
+            lines: vec![
+                (0, 1, 1.0, Crease::Border),
+                (1, 2, 1.0, Crease::Border),
+                (2, 3, 1.0, Crease::Border),
+                (3, 0, 1.0, Crease::Border),
+            ],
+        }
+    }
+
+    fn area(model: &Model) -> f32 {
+        model
+            .triangles
+            .iter()
+            .map(|&(a, b, c)| {
+                let v = |i: u32| model.vertices[i as usize];
+                (v(b) - v(a)).cross(&(v(c) - v(a))).z * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_cut_triangles() {
+        let fold = CutFold::line(Vec2::new(1.0, 0.0), Vec2::new(1.0, 2.0), PI);
+        let model = fold.apply(square(), 0.0);
+
+        assert_eq!(model.vertices.len(), 7);
+        assert_eq!(model.vertices[4], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(model.vertices[6], Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(model.triangles.len(), 6);
+        // Winding is kept, so the flat area doesn't change sign
+        assert!((area(&model) - 4.0).abs() < 1e-6);
+        assert!(model.validate().is_valid(), "{}", model.validate());
+        assert_eq!(model.lines.len(), 8);
+        assert!(model.lines.contains(&(4, 5, 0.0, Crease::Valley)));
+        assert!(model.lines.contains(&(5, 6, 0.0, Crease::Valley)));
+    }
+
+    #[test]
+    fn test_fold_left_side() {
+        let fold = CutFold::line(Vec2::new(1.0, 0.0), Vec2::new(1.0, 2.0), PI);
+        let model = fold.apply(square(), 1.0);
+
+        assert!((model.vertices[0] - Vec3::new(2.0, 0.0, 0.0)).norm() < 1e-6);
+        assert!((model.vertices[3] - Vec3::new(2.0, 2.0, 0.0)).norm() < 1e-6);
+        assert_eq!(model.vertices[1], Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(model.vertices[5], Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_cut_through_vertex() {
+        let fold = CutFold::line(Vec2::new(0.0, 0.0), Vec2::new(2.0, 1.0), PI * 0.5);
+        let model = fold.apply(square(), 0.0);
+
+        // Only the triangle with the crossed edge is split, into two parts
+        assert_eq!(model.vertices.len(), 5);
+        assert!((model.vertices[4] - Vec3::new(2.0, 1.0, 0.0)).norm() < 1e-6);
+        assert_eq!(model.triangles.len(), 3);
+        assert!(model.lines.contains(&(0, 4, 0.0, Crease::Valley)));
+        assert!(model.validate().is_valid(), "{}", model.validate());
+    }
+
+    #[test]
+    fn test_folds() {
+        let fold = CutFold::line(Vec2::new(1.0, 0.0), Vec2::new(1.0, 2.0), PI * 0.5);
+        let folds = fold.folds(&square());
+        assert_eq!(
+            folds,
+            vec![Fold::new((4, 6), BitSet::with_bits(&[0, 3]), PI * 0.5)]
+        );
+        // The fold moves the cut model as the cut fold does
+        let cut = fold.apply(square(), 0.0);
+        let folded = folds[0].apply(cut, 1.0);
+        let expected = fold.apply(square(), 1.0);
+        for (v, e) in folded.vertices.iter().zip(expected.vertices.iter()) {
+            assert!((v - e).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_plane_missing_model() {
+        let fold = CutFold::new(Vec3::new(5.0, 0.0, 0.0), Vec3::y(), Vec3::x(), PI);
+        let model = fold.apply(square(), 1.0);
+        assert_eq!(model.vertices, square().vertices);
+        assert_eq!(model.triangles, square().triangles);
+        assert!(fold.folds(&square()).is_empty());
+    }
+}
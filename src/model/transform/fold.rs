@@ -1,24 +1,204 @@
-use glm::{rotation, translation, vec4_to_vec3, Mat4};
+use glm::{rotation, translation, vec4_to_vec3, Mat4, Vec3};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
 use crate::bit_set::BitSet;
+use crate::model::adjacency::Adjacency;
 use crate::model::transform::transform::Transform;
+use crate::model::transform::Bend;
 use crate::model::Model;
 
+/// Vertices closer to the fold line than this part of its length are on the line
+const ON_LINE_TOLERANCE: f32 = 1e-4;
+
+/// Part of the model that is moved by a fold
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FoldSide {
+    /// Everything on the same side of the fold line as the point, through all layers
+    Point(Vec3),
+    /// Faces connected to the vertex without crossing the fold line
+    Component(u32),
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FoldError {
+    VertexOutOfRange {
+        vertex: u32,
+    },
+    DegenerateLine {
+        line: (u32, u32),
+    },
+    /// Side point or vertex is on the fold line, so it doesn't select a side
+    AmbiguousSide,
+    /// Some vertices of the triangle move and some stay
+    TearsFace {
+        triangle: usize,
+    },
+}
+
+impl Display for FoldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FoldError::VertexOutOfRange { vertex } => {
+                write!(f, "vertex {} is out of the model", vertex)
+            }
+            FoldError::DegenerateLine { line } => {
+                write!(f, "fold line {:?} has zero length", line)
+            }
+            FoldError::AmbiguousSide => write!(f, "fold side is on the fold line"),
+            FoldError::TearsFace { triangle } => {
+                write!(f, "fold tears triangle {}", triangle)
+            }
+        }
+    }
+}
+
+impl Error for FoldError {}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Fold {
-    line: (u8, u8),
+    line: (u32, u32),
     points: BitSet,
     angle: f32,
 }
 
 impl Fold {
-    pub const fn new(line: (u8, u8), points: BitSet, angle: f32) -> Fold {
+    pub const fn new(line: (u32, u32), points: BitSet, angle: f32) -> Fold {
         Fold {
             line,
             points,
             angle,
         }
     }
+
+    /// Fold with the moving vertices found from the current state of the model.
+    /// Vertices on the fold line don't move, so they are never in the moving set.
+    pub fn with_side(
+        model: &Model,
+        line: (u32, u32),
+        side: FoldSide,
+        angle: f32,
+    ) -> Result<Fold, FoldError> {
+        let vertex = |i: u32| {
+            model
+                .vertices
+                .get(i as usize)
+                .copied()
+                .ok_or(FoldError::VertexOutOfRange { vertex: i })
+        };
+        let p0 = vertex(line.0)?;
+        let direction = vertex(line.1)? - p0;
+        let length = direction.norm();
+        if length == 0.0 {
+            return Err(FoldError::DegenerateLine { line });
+        }
+        let direction = direction / length;
+        let tolerance = length * ON_LINE_TOLERANCE;
+        // Offset of the point from the fold line, perpendicular to it
+        let offset = |p: &Vec3| {
+            let p = p - p0;
+            p - direction * p.dot(&direction)
+        };
+        let on_line: Vec<bool> = model
+            .vertices
+            .iter()
+            .map(|v| offset(v).norm() <= tolerance)
+            .collect();
+
+        let points: BitSet = match side {
+            FoldSide::Point(point) => {
+                let normal = offset(&point);
+                if normal.norm() <= tolerance {
+                    return Err(FoldError::AmbiguousSide);
+                }
+                let normal = normal.normalize();
+                let mut points = BitSet::new();
+                for (i, v) in model.vertices.iter().enumerate() {
+                    if !on_line[i] && offset(v).dot(&normal) > 0.0 {
+                        points.insert(i as u32);
+                    }
+                }
+                points
+            }
+            FoldSide::Component(start) => {
+                vertex(start)?;
+                if on_line[start as usize] {
+                    return Err(FoldError::AmbiguousSide);
+                }
+                let adjacency = Adjacency::new(model);
+                let mut moving = vec![false; adjacency.faces_count()];
+                let mut queue = adjacency.vertex_faces(start);
+                for &face in queue.iter() {
+                    moving[face] = true;
+                }
+                while let Some(face) = queue.pop() {
+                    for &h in adjacency.face_half_edges(face).iter() {
+                        let (a, b) = adjacency.vertices(h);
+                        if on_line[a as usize] && on_line[b as usize] {
+                            continue;
+                        }
+                        for next in adjacency.edge_faces(a, b) {
+                            if !moving[next] {
+                                moving[next] = true;
+                                queue.push(next);
+                            }
+                        }
+                    }
+                }
+
+                let mut points = BitSet::new();
+                points.insert(start);
+                for (i, &(a, b, c)) in model.triangles.iter().enumerate() {
+                    if moving[i] {
+                        for &v in [a, b, c].iter().filter(|&&v| !on_line[v as usize]) {
+                            points.insert(v);
+                        }
+                    }
+                }
+                points
+            }
+        };
+
+        for (i, &(a, b, c)) in model.triangles.iter().enumerate() {
+            let (moving, staying): (Vec<u32>, Vec<u32>) = [a, b, c]
+                .iter()
+                .filter(|&&v| !on_line[v as usize])
+                .partition(|&&v| points.has(v));
+            if !moving.is_empty() && !staying.is_empty() {
+                return Err(FoldError::TearsFace { triangle: i });
+            }
+        }
+
+        Ok(Fold::new(line, points, angle))
+    }
+
+    pub fn line(&self) -> (u32, u32) {
+        self.line
+    }
+
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    /// The same fold rolling the paper around a cylinder of `radius` near the crease
+    pub fn bend(self, radius: f32) -> Bend {
+        Bend::new(self.line, self.points, self.angle, radius)
+    }
+
+    /// The same fold with vertex `i` moved to index `map[i]`
+    pub fn reindex(&self, map: &[u32]) -> Fold {
+        Fold::new(
+            (map[self.line.0 as usize], map[self.line.1 as usize]),
+            self.points.iter().map(|i| map[i as usize]).collect(),
+            self.angle,
+        )
+    }
+
+    /// Fold that undoes this one
+    pub fn reversed(&self) -> Fold {
+        Fold::new(self.line, self.points.clone(), -self.angle)
+    }
 }
 
 impl Transform for Fold {
@@ -32,18 +212,23 @@ impl Transform for Fold {
             * translation(&(-p0));
 
         for i in 0..vertices.len() {
-            if self.points.has(i as u8) {
+            if self.points.has(i as u32) {
                 vertices[i] = vec4_to_vec3(&(transformation * vertices[i].push(1.0)));
             }
         }
 
         Model { vertices, ..model }
     }
+
+    fn folds(&self, _model: &Model) -> Vec<Fold> {
+        vec![self.clone()]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use glm::Vec3;
 
     fn round(v: Vec3) -> Vec3 {
         Vec3::new(
@@ -117,4 +302,51 @@ mod tests {
         assert_eq!(v1, Vec3::new(1.0, 2.7109375, 3.7109375));
         assert_eq!(v2, Vec3::new(1.0, 2.0, 4.0));
     }
+
+    /// Two squares side by side with the fold line (1, 4) between them
+    fn strip() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(2.0, 1.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 4), (0, 4, 5), (1, 2, 3), (1, 3, 4)],
+            lines: vec![],
+        }
+    }
+
+    #[test]
+    fn test_side_component() {
+        let fold = Fold::with_side(&strip(), (1, 4), FoldSide::Component(2), 1.0).unwrap();
+        assert_eq!(fold.points, BitSet::with_bits(&[2, 3]));
+    }
+
+    #[test]
+    fn test_side_point() {
+        let side = FoldSide::Point(Vec3::new(-1.0, 0.5, 0.0));
+        let fold = Fold::with_side(&strip(), (1, 4), side, 1.0).unwrap();
+        assert_eq!(fold.points, BitSet::with_bits(&[0, 5]));
+    }
+
+    #[test]
+    fn test_side_errors() {
+        let model = strip();
+        assert_eq!(
+            Fold::with_side(&model, (1, 4), FoldSide::Component(4), 1.0),
+            Err(FoldError::AmbiguousSide)
+        );
+        assert_eq!(
+            Fold::with_side(&model, (1, 6), FoldSide::Component(2), 1.0),
+            Err(FoldError::VertexOutOfRange { vertex: 6 })
+        );
+        let side = FoldSide::Point(Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(
+            Fold::with_side(&model, (0, 3), side, 1.0),
+            Err(FoldError::TearsFace { triangle: 0 })
+        );
+    }
 }
@@ -0,0 +1,158 @@
+use crate::model::{Model, Transform};
+use glm::{
+    determinant, mat3_to_mat4, mat3_to_quat, mat4_to_mat3, rotation, translation, vec4_to_vec3,
+    Mat3, Mat4, Vec3,
+};
+
+/// Any affine transformation of the whole model. The matrix is split into translation,
+/// rotation and a stretch with the scale and shear, which are interpolated separately,
+/// so the model turns along the shortest arc instead of shrinking on the way.
+pub struct Matrix {
+    translation: Vec3,
+    axis: Vec3,
+    angle: f32,
+    stretch: Mat3,
+}
+
+/// Split the linear part into a rotation and an upper triangular stretch, with the scale
+/// on the diagonal and the shear above it. Mirroring is a negative scale along x.
+fn decompose(linear: &Mat3) -> (Mat3, Mat3) {
+    let mut rotation = Mat3::zeros();
+    for i in 0..3 {
+        let remainder = |column: Vec3| {
+            (0..i).fold(column, |column, j| {
+                let axis = rotation.column(j).clone_owned();
+                column - axis * axis.dot(&column)
+            })
+        };
+        let mut column = remainder(linear.column(i).clone_owned());
+        if column.norm() < 1e-6 {
+            // Flattened column, any direction that completes the rotation works
+            column = [Vec3::x(), Vec3::y(), Vec3::z()]
+                .iter()
+                .map(|&axis| remainder(axis))
+                .max_by(|a, b| a.norm().partial_cmp(&b.norm()).unwrap())
+                .unwrap();
+        }
+        rotation.set_column(i, &column.normalize());
+    }
+    if determinant(&rotation) < 0.0 {
+        let mirrored = -rotation.column(0);
+        rotation.set_column(0, &mirrored);
+    }
+    let stretch = rotation.transpose() * linear;
+    (rotation, stretch)
+}
+
+impl Matrix {
+    pub fn new(matrix: Mat4) -> Self {
+        let (rotation, stretch) = decompose(&mat4_to_mat3(&matrix));
+
+        // Quaternion with the positive real part turns by at most a half turn
+        let quaternion = mat3_to_quat(&rotation);
+        let sign = if quaternion.w < 0.0 { -1.0 } else { 1.0 };
+        let imaginary = quaternion.imag() * sign;
+        let angle = 2.0 * imaginary.norm().atan2(quaternion.w * sign);
+        let axis = if imaginary.norm() > 0.0 {
+            imaginary.normalize()
+        } else {
+            Vec3::x()
+        };
+
+        Self {
+            translation: vec4_to_vec3(&matrix.column(3).into()),
+            axis,
+            angle,
+            stretch,
+        }
+    }
+
+    fn at(&self, t: f32) -> Mat4 {
+        translation(&(self.translation * t))
+            * rotation(self.angle * t, &self.axis)
+            * mat3_to_mat4(&(Mat3::identity() * (1.0 - t) + self.stretch * t))
+    }
+}
+
+impl Transform for Matrix {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        let transformation = self.at(t);
+        let mut vertices = model.vertices;
+        for vertex in vertices.iter_mut() {
+            *vertex = vec4_to_vec3(&(transformation * vertex.push(1.0)));
+        }
+        Model { vertices, ..model }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::scaling;
+    use std::f32::consts::PI;
+
+    fn model() -> Model {
+        Model {
+            vertices: vec![Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 1.0)],
+            triangles: vec![],
+            lines: vec![],
+        }
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(
+            (actual - expected).norm() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_ends_at_matrix() {
+        let matrix = translation(&Vec3::new(1.0, 2.0, 3.0))
+            * rotation(PI * 0.75, &Vec3::new(1.0, 1.0, 0.0).normalize())
+            * scaling(&Vec3::new(2.0, 0.5, -1.0));
+        let model = Matrix::new(matrix).apply(model(), 1.0);
+        for (actual, vertex) in model.vertices.iter().zip(self::model().vertices.iter()) {
+            assert_near(*actual, vec4_to_vec3(&(matrix * vertex.push(1.0))));
+        }
+        assert_eq!(
+            Matrix::new(matrix).apply(self::model(), 0.0).vertices,
+            self::model().vertices
+        );
+    }
+
+    #[test]
+    fn test_ends_at_sheared_matrix() {
+        let shear = Mat4::new(
+            1.0, 0.5, -0.25, 0.0, //
+            0.0, 1.0, 0.75, 0.0, //
+            0.0, 0.0, 1.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0,
+        );
+        for &scale in [Vec3::new(2.0, 1.0, 0.5), Vec3::new(1.0, -1.0, 0.0)].iter() {
+            let matrix =
+                rotation(PI * 0.4, &Vec3::new(0.0, 1.0, 1.0).normalize()) * shear * scaling(&scale);
+            let model = Matrix::new(matrix).apply(model(), 1.0);
+            for (actual, vertex) in model.vertices.iter().zip(self::model().vertices.iter()) {
+                assert_near(*actual, vec4_to_vec3(&(matrix * vertex.push(1.0))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotation_keeps_size() {
+        let matrix = Matrix::new(rotation(PI * 0.5, &Vec3::z()));
+        let model = matrix.apply(model(), 0.5);
+        let half = (0.5f32).sqrt();
+        assert_near(model.vertices[0], Vec3::new(half, half, 0.0));
+        assert_near(model.vertices[1], Vec3::new(-half, half, 1.0));
+    }
+
+    #[test]
+    fn test_identity() {
+        let matrix = Matrix::new(Mat4::identity());
+        assert_eq!(matrix.apply(model(), 0.5).vertices, model().vertices);
+    }
+}
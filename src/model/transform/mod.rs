@@ -1,12 +1,23 @@
 mod add_lines;
+mod bend;
+mod cut_fold;
 pub mod fold;
-mod rotate_x;
+mod matrix;
+mod rigid_fold;
+mod rotate;
+mod scale;
 mod shift;
 mod shift_all;
 mod stay;
 mod transform;
 mod transform_parallel;
 
-pub use rotate_x::RotateX;
+pub use bend::Bend;
+#[allow(unused_imports)]
+pub use cut_fold::CutFold;
+pub use matrix::Matrix;
+pub use rigid_fold::{RigidFold, RigidFoldError};
+pub use rotate::{Rotate, RotationAxis};
+pub use scale::Scale;
 pub use stay::Stay;
 pub use transform::Transform;
@@ -0,0 +1,627 @@
+use crate::bit_set::BitSet;
+use crate::model::adjacency::Adjacency;
+use crate::model::creases::fold_angle;
+use crate::model::transform::fold::Fold;
+use crate::model::transform::transform::Transform;
+use crate::model::{Crease, Model};
+use glm::Vec3;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt::{Display, Formatter};
+
+/// Steps the fold is split into, so every step starts close to the solution
+const SUBSTEPS: usize = 16;
+/// Passes over all constraints in every step
+const ITERATIONS: usize = 64;
+/// Constraints with gradients below this are degenerate and skipped
+const MIN_GRADIENT: f32 = 1e-12;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RigidFoldError {
+    /// Line is not an edge of the model triangles
+    NotAnEdge {
+        line: (u32, u32),
+    },
+    /// Edge doesn't have a face on both sides, so it has no fold angle
+    BorderEdge {
+        line: (u32, u32),
+    },
+    FaceOutOfRange {
+        face: usize,
+    },
+}
+
+impl Display for RigidFoldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RigidFoldError::NotAnEdge { line } => {
+                write!(f, "line {:?} is not an edge of the model", line)
+            }
+            RigidFoldError::BorderEdge { line } => {
+                write!(f, "edge {:?} is on the border of the paper", line)
+            }
+            RigidFoldError::FaceOutOfRange { face } => {
+                write!(f, "face {} is out of the model", face)
+            }
+        }
+    }
+}
+
+impl Error for RigidFoldError {}
+
+/// Fold of several creases at once with rigid faces, like a collapse of a crease pattern.
+/// Creases with targets turn to their fold angles, positive for valley folds as in
+/// `fold_angle`, free creases turn as the others need, and all other edges keep their
+/// angles. The vertices are found by moving them until the edge lengths and the angles
+/// match, so creases that can't fold together get as close as the paper lets them,
+/// see `residual`. The faces of the `fixed` triangle don't move.
+///
+/// The fold is solved once for the model it's made for, on the first use, and every
+/// state of it starts from the nearest solved step with the constraints found then.
+/// Other models are solved from the start.
+pub struct RigidFold {
+    /// The model the fold is made for
+    model: Model,
+    targets: HashMap<(u32, u32), f32>,
+    free: Vec<(u32, u32)>,
+    fixed: usize,
+    /// Constraints of the `model`
+    constraints: OnceCell<Constraints>,
+    /// Vertices of the `model` at the ends of the steps, from the start to the end
+    path: OnceCell<Vec<Vec<Vec3>>>,
+}
+
+/// Edge with the two faces folded around it
+struct Hinge {
+    /// Edge from the lower vertex to the higher one, then the opposite vertices
+    /// of the face going along the edge and of the face going backwards
+    vertices: [usize; 4],
+    angle: f32,
+    /// Turn of the angle at the end of the fold
+    turn: f32,
+}
+
+/// What the fold keeps or changes in a model
+struct Constraints {
+    hinges: Vec<Hinge>,
+    /// Edges with their lengths
+    lengths: Vec<((usize, usize), f32)>,
+    /// Vertices of the fixed face
+    fixed: BitSet,
+}
+
+impl Constraints {
+    fn weight(&self, i: usize) -> f32 {
+        if self.fixed.has(i as u32) {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Move the `vertices` closer to the state at `progress` of the fold
+    fn relax(&self, vertices: &mut [Vec3], progress: f32) {
+        for _ in 0..ITERATIONS {
+            for hinge in self.hinges.iter() {
+                let points = hinge.vertices.map(|i| vertices[i]);
+                let (angle, gradients) = match dihedral(points) {
+                    Some(dihedral) => dihedral,
+                    None => continue,
+                };
+                let error = wrap(angle - hinge.angle - hinge.turn * progress);
+                let moved = || hinge.vertices.iter().copied().zip(gradients.iter());
+                let norm: f32 = moved()
+                    .map(|(i, g)| self.weight(i) * g.norm_squared())
+                    .sum();
+                if norm < MIN_GRADIENT {
+                    continue;
+                }
+                for (i, gradient) in moved() {
+                    vertices[i] -= gradient * (self.weight(i) * error / norm);
+                }
+            }
+            for &((a, b), length) in self.lengths.iter() {
+                let (wa, wb) = (self.weight(a), self.weight(b));
+                let offset = vertices[b] - vertices[a];
+                let distance = offset.norm();
+                if wa + wb == 0.0 || distance == 0.0 {
+                    continue;
+                }
+                let correction = offset * ((distance - length) / (distance * (wa + wb)));
+                vertices[a] += correction * wa;
+                vertices[b] -= correction * wb;
+            }
+        }
+    }
+
+    /// The largest error of a hinge angle, in radians, or of an edge length,
+    /// in parts of the length, at `progress` of the fold
+    fn residual(&self, vertices: &[Vec3], progress: f32) -> f32 {
+        let angles = self.hinges.iter().filter_map(|hinge| {
+            let (angle, _) = dihedral(hinge.vertices.map(|i| vertices[i]))?;
+            Some(wrap(angle - hinge.angle - hinge.turn * progress).abs())
+        });
+        let lengths = self.lengths.iter().map(|&((a, b), length)| {
+            (glm::distance(&vertices[a], &vertices[b]) - length).abs() / length
+        });
+        angles.chain(lengths).fold(0.0, f32::max)
+    }
+}
+
+fn edge_key((a, b): (u32, u32)) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// Angle turned to the range above -PI up to PI
+fn wrap(angle: f32) -> f32 {
+    angle - ((angle - PI) / (2.0 * PI)).ceil() * 2.0 * PI
+}
+
+/// Fold angle of the hinge and its gradients by the hinge vertices
+fn dihedral(points: [Vec3; 4]) -> Option<(f32, [Vec3; 4])> {
+    let [a, b, c, d] = points;
+    let edge = b - a;
+    let length = edge.norm();
+    let n1 = edge.cross(&(c - a));
+    let n2 = (a - b).cross(&(d - b));
+    let (area1, area2) = (n1.norm_squared(), n2.norm_squared());
+    if length == 0.0 || area1 == 0.0 || area2 == 0.0 {
+        return None;
+    }
+    let angle = (-n1.cross(&n2).dot(&edge) / length).atan2(n1.dot(&n2));
+
+    // Opposite vertices turn the faces around the edge, moving the edge vertices
+    // turns the faces around the opposite ones
+    let gc = n1 * (length / area1);
+    let gd = n2 * (length / area2);
+    let sc = (c - a).dot(&edge) / (length * length);
+    let sd = (d - a).dot(&edge) / (length * length);
+    let ga = -gc * (1.0 - sc) - gd * (1.0 - sd);
+    let gb = -gc * sc - gd * sd;
+    Some((angle, [ga, gb, gc, gd]))
+}
+
+/// Vertex of the triangle that is not on the edge
+fn opposite(model: &Model, face: usize, (a, b): (u32, u32)) -> usize {
+    let (x, y, z) = model.triangles[face];
+    [x, y, z]
+        .iter()
+        .copied()
+        .find(|&v| v != a && v != b)
+        .unwrap_or(x) as usize
+}
+
+impl RigidFold {
+    /// Fold the creases of the `model` to the `targets` angles, `free` creases turn
+    /// as the others need
+    pub fn new(
+        model: &Model,
+        targets: Vec<((u32, u32), f32)>,
+        free: Vec<(u32, u32)>,
+    ) -> Result<Self, RigidFoldError> {
+        let adjacency = Adjacency::new(model);
+        for &line in targets.iter().map(|(line, _)| line).chain(free.iter()) {
+            let edge = adjacency
+                .edge(line.0, line.1)
+                .ok_or(RigidFoldError::NotAnEdge { line })?;
+            adjacency
+                .sides(edge)
+                .ok_or(RigidFoldError::BorderEdge { line })?;
+        }
+        Ok(Self {
+            model: model.clone(),
+            targets: targets
+                .into_iter()
+                .map(|(line, angle)| (edge_key(line), angle))
+                .collect(),
+            free: free.into_iter().map(edge_key).collect(),
+            fixed: 0,
+            constraints: OnceCell::new(),
+            path: OnceCell::new(),
+        })
+    }
+
+    /// Fold all mountain and valley lines of the `model` by the `angle`,
+    /// like collapsing a crease pattern
+    pub fn creases(model: &Model, angle: f32) -> Result<Self, RigidFoldError> {
+        let targets = model
+            .lines
+            .iter()
+            .filter_map(|&(a, b, _, crease)| match crease {
+                Crease::Valley => Some(((a, b), angle)),
+                Crease::Mountain => Some(((a, b), -angle)),
+                _ => None,
+            })
+            .collect();
+        Self::new(model, targets, vec![])
+    }
+
+    /// Triangle that keeps its place, the first one by default
+    pub fn fixed(self, face: usize) -> Result<Self, RigidFoldError> {
+        if face >= self.model.triangles.len() {
+            return Err(RigidFoldError::FaceOutOfRange { face });
+        }
+        Ok(Self {
+            fixed: face,
+            constraints: OnceCell::new(),
+            path: OnceCell::new(),
+            ..self
+        })
+    }
+
+    /// The largest error of a hinge angle, in radians, or of an edge length, in parts
+    /// of the length, at the end of the fold. Creases that can't fold together
+    /// leave a large error.
+    #[allow(dead_code)]
+    pub fn residual(&self) -> f32 {
+        let end = self.path().last().expect("path has the start");
+        self.constraints().residual(end, 1.0)
+    }
+
+    /// Constraints of the `model` the fold is made for
+    fn constraints(&self) -> &Constraints {
+        self.constraints
+            .get_or_init(|| self.constraints_of(&self.model))
+    }
+
+    fn constraints_of(&self, model: &Model) -> Constraints {
+        let adjacency = Adjacency::new(model);
+        let mut hinges = Vec::new();
+        for edge in adjacency.edges() {
+            if self.free.contains(&edge.vertices) {
+                continue;
+            }
+            let (left, right) = match adjacency.sides(edge) {
+                Some(sides) => sides,
+                None => continue,
+            };
+            let angle = match fold_angle(model, &adjacency, edge) {
+                Some(angle) => angle,
+                None => continue,
+            };
+            let turn = match self.targets.get(&edge.vertices) {
+                Some(target) => wrap(target - angle),
+                None => 0.0,
+            };
+            let (a, b) = edge.vertices;
+            hinges.push(Hinge {
+                vertices: [
+                    a as usize,
+                    b as usize,
+                    opposite(model, left, edge.vertices),
+                    opposite(model, right, edge.vertices),
+                ],
+                angle,
+                turn,
+            });
+        }
+        let lengths = adjacency
+            .edges()
+            .iter()
+            .map(|edge| {
+                let (a, b) = (edge.vertices.0 as usize, edge.vertices.1 as usize);
+                (
+                    (a, b),
+                    glm::distance(&model.vertices[a], &model.vertices[b]),
+                )
+            })
+            .collect();
+        let fixed = match model.triangles.get(self.fixed) {
+            Some(&(a, b, c)) => BitSet::with_bits(&[a, b, c]),
+            None => BitSet::new(),
+        };
+        Constraints {
+            hinges,
+            lengths,
+            fixed,
+        }
+    }
+
+    /// Vertices of the `model` the fold is made for at the ends of the steps
+    fn path(&self) -> &[Vec<Vec3>] {
+        self.path.get_or_init(|| {
+            let constraints = self.constraints();
+            let mut path = vec![self.model.vertices.clone()];
+            for step in 1..=SUBSTEPS {
+                let mut vertices = path[step - 1].clone();
+                constraints.relax(&mut vertices, step as f32 / SUBSTEPS as f32);
+                path.push(vertices);
+            }
+            path
+        })
+    }
+
+    /// Vertices at `t` of the fold of any model, found step by step from the start
+    fn solve(&self, model: &Model, t: f32) -> Vec<Vec3> {
+        let constraints = self.constraints_of(model);
+        let mut vertices = model.vertices.clone();
+        for step in 1..=SUBSTEPS {
+            constraints.relax(&mut vertices, t * step as f32 / SUBSTEPS as f32);
+        }
+        vertices
+    }
+
+    /// Faces reached from the `face` without crossing a crease that turns,
+    /// without the vertices of the `line`
+    fn side(&self, adjacency: &Adjacency, face: usize, line: (u32, u32)) -> BitSet {
+        let turns = |edge: (u32, u32)| {
+            let edge = edge_key(edge);
+            self.free.contains(&edge) || self.targets.contains_key(&edge)
+        };
+        let mut reached = vec![false; adjacency.faces_count()];
+        let mut queue = vec![face];
+        reached[face] = true;
+        let mut points = BitSet::new();
+        while let Some(face) = queue.pop() {
+            for &half_edge in adjacency.face_half_edges(face).iter() {
+                let (a, _) = adjacency.vertices(half_edge);
+                points.insert(a);
+                if turns(adjacency.vertices(half_edge)) {
+                    continue;
+                }
+                if let Some(twin) = adjacency.twin(half_edge) {
+                    let next = adjacency.face(twin);
+                    if !reached[next] {
+                        reached[next] = true;
+                        queue.push(next);
+                    }
+                }
+            }
+        }
+        points.remove(line.0);
+        points.remove(line.1);
+        points
+    }
+}
+
+impl Transform for RigidFold {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        let vertices =
+            if model.vertices == self.model.vertices && model.triangles == self.model.triangles {
+                let path = self.path();
+                let position = t.clamp(0.0, 1.0) * SUBSTEPS as f32;
+                let step = position.floor() as usize;
+                let mut vertices = path[step].clone();
+                if position > step as f32 {
+                    self.constraints().relax(&mut vertices, t);
+                }
+                vertices
+            } else {
+                self.solve(&model, t)
+            };
+        Model { vertices, ..model }
+    }
+
+    /// Every crease that turns as a fold of the faces on the side of the face going
+    /// along it, up to the other turning creases. The folds show the creases and
+    /// their angles, the rigid fold is not the same as doing them one by one.
+    fn folds(&self, _model: &Model) -> Vec<Fold> {
+        let adjacency = Adjacency::new(&self.model);
+        let end = Model {
+            vertices: self.path().last().expect("path has the start").clone(),
+            ..self.model.clone()
+        };
+        let mut lines: Vec<(u32, u32)> = self
+            .targets
+            .keys()
+            .copied()
+            .chain(self.free.iter().copied())
+            .collect();
+        lines.sort_unstable();
+        lines
+            .into_iter()
+            .filter_map(|line| {
+                let edge = adjacency.edge(line.0, line.1)?;
+                let (left, _) = adjacency.sides(edge)?;
+                let start = fold_angle(&self.model, &adjacency, edge)?;
+                let turn = match self.targets.get(&line) {
+                    Some(target) => wrap(target - start),
+                    None => wrap(fold_angle(&end, &adjacency, edge)? - start),
+                };
+                Some(Fold::new(line, self.side(&adjacency, left, line), turn))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::transform::fold::Fold;
+
+    fn square() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3)],
+            lines: vec![],
+        }
+    }
+
+    /// Degree-4 vertex with sector angles of 45, 90, 135 and 90 degrees,
+    /// the creases are the edges from the centre
+    fn bird_foot() -> Model {
+        let direction = |degrees: f32| {
+            let angle = degrees.to_radians();
+            Vec3::new(angle.cos(), angle.sin(), 0.0)
+        };
+        Model {
+            vertices: vec![
+                Vec3::zeros(),
+                direction(0.0),
+                direction(45.0),
+                direction(135.0),
+                direction(270.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3), (0, 3, 4), (0, 4, 1)],
+            lines: vec![],
+        }
+    }
+
+    fn angle(model: &Model, line: (u32, u32)) -> f32 {
+        let adjacency = Adjacency::new(model);
+        fold_angle(model, &adjacency, adjacency.edge(line.0, line.1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_dihedral_gradients() {
+        let points = [
+            Vec3::new(0.1, 0.0, 0.2),
+            Vec3::new(1.0, 0.2, 0.0),
+            Vec3::new(0.4, 1.0, 0.3),
+            Vec3::new(0.6, -0.8, 0.5),
+        ];
+        let (angle, gradients) = dihedral(points).unwrap();
+        let step = 1e-3;
+        for k in 0..4 {
+            for axis in 0..3 {
+                let mut moved = points;
+                moved[k][axis] += step;
+                let numeric = (dihedral(moved).unwrap().0 - angle) / step;
+                assert!(
+                    (numeric - gradients[k][axis]).abs() < 1e-2,
+                    "vertex {} axis {}: {} != {}",
+                    k,
+                    axis,
+                    numeric,
+                    gradients[k][axis]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_crease_matches_fold() {
+        let fold = RigidFold::new(&square(), vec![((2, 0), PI * 0.5)], vec![]).unwrap();
+        let expected = Fold::new((0, 2), BitSet::with_bits(&[3]), PI * 0.5);
+        for &t in [0.5, 1.0].iter() {
+            let model = fold.apply(square(), t);
+            let folded = expected.apply(square(), t);
+            for (a, b) in model.vertices.iter().zip(folded.vertices.iter()) {
+                assert!((a - b).norm() < 1e-3, "{} != {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_free_creases_follow() {
+        let model = bird_foot();
+        let free = vec![(0, 2), (0, 3), (0, 4)];
+        let fold = RigidFold::new(&model, vec![((0, 1), PI * 0.5)], free.clone()).unwrap();
+        let folded = fold.apply(model.clone(), 1.0);
+        assert!(fold.residual() < 1e-3, "residual {}", fold.residual());
+
+        assert!((angle(&folded, (0, 1)) - PI * 0.5).abs() < 1e-2);
+        // The vertex can't fold one crease alone, so all of them turn
+        for &line in free.iter() {
+            assert!(angle(&folded, line).abs() > 0.1, "{:?} is flat", line);
+        }
+        for &(a, b) in [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (2, 3),
+            (3, 4),
+            (4, 1),
+        ]
+        .iter()
+        {
+            let length = |m: &Model| glm::distance(&m.vertices[a], &m.vertices[b]);
+            assert!((length(&folded) - length(&model)).abs() < 1e-3);
+        }
+        assert_eq!(folded.vertices[..3], model.vertices[..3]);
+    }
+
+    #[test]
+    fn test_kept_edges() {
+        // Without free creases the other faces can't move, so the crease doesn't fold
+        let model = bird_foot();
+        let fold = RigidFold::new(&model, vec![((0, 1), PI * 0.5)], vec![]).unwrap();
+        let folded = fold.apply(model, 1.0);
+        assert!(angle(&folded, (0, 1)) < PI * 0.25);
+        assert!(fold.residual() > PI * 0.25, "residual {}", fold.residual());
+    }
+
+    #[test]
+    fn test_warm_start() {
+        let model = bird_foot();
+        let free = vec![(0, 2), (0, 3), (0, 4)];
+        let fold = RigidFold::new(&model, vec![((0, 1), PI * 0.5)], free).unwrap();
+        let constraints = fold.constraints();
+        for &t in [0.3, 0.5, 0.99, 1.0].iter() {
+            let folded = fold.apply(model.clone(), t);
+            let residual = constraints.residual(&folded.vertices, t);
+            assert!(residual < 1e-2, "t {}: residual {}", t, residual);
+        }
+        // States between the solved steps start from the step before
+        let between = fold.apply(model.clone(), 0.3);
+        let step = fold.apply(model.clone(), 0.25);
+        for (a, b) in between.vertices.iter().zip(step.vertices.iter()) {
+            assert!((a - b).norm() < 0.1, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_folds() {
+        let model = bird_foot();
+        let fold = RigidFold::new(
+            &model,
+            vec![((1, 0), PI * 0.5)],
+            vec![(0, 2), (0, 3), (0, 4)],
+        )
+        .unwrap();
+        let folds = fold.folds(&model);
+        assert_eq!(folds.len(), 4);
+        assert_eq!(
+            folds[0],
+            Fold::new((0, 1), BitSet::with_bits(&[2]), PI * 0.5)
+        );
+        let folded = fold.apply(model.clone(), 1.0);
+        for fold in folds[1..].iter() {
+            assert!((fold.angle() - angle(&folded, fold.line())).abs() < 1e-3);
+        }
+
+        // Flat folds turn the way of the target
+        let fold = RigidFold::new(&square(), vec![((0, 2), PI)], vec![]).unwrap();
+        assert_eq!(fold.folds(&square())[0].angle(), PI);
+        assert!(fold.apply(square(), 1.0).vertices[3].z.abs() < 1e-3);
+        assert!(fold.apply(square(), 0.5).vertices[3].z > 0.5);
+    }
+
+    #[test]
+    fn test_creases() {
+        let mut model = square();
+        model.lines = vec![(0, 2, 1.0, Crease::Mountain), (0, 1, 1.0, Crease::Border)];
+        let folded = RigidFold::creases(&model, PI * 0.5)
+            .unwrap()
+            .apply(model, 1.0);
+        assert!((angle(&folded, (0, 2)) + PI * 0.5).abs() < 1e-2);
+        assert!(folded.vertices[3].z < -0.5);
+    }
+
+    #[test]
+    fn test_errors() {
+        let new = |line| RigidFold::new(&square(), vec![(line, 1.0)], vec![]).err();
+        assert_eq!(
+            new((1, 3)),
+            Some(RigidFoldError::NotAnEdge { line: (1, 3) })
+        );
+        assert_eq!(
+            new((0, 1)),
+            Some(RigidFoldError::BorderEdge { line: (0, 1) })
+        );
+        let fold = RigidFold::new(&square(), vec![((0, 2), 1.0)], vec![]).unwrap();
+        assert_eq!(
+            fold.fixed(2).err(),
+            Some(RigidFoldError::FaceOutOfRange { face: 2 })
+        );
+    }
+}
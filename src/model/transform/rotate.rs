@@ -0,0 +1,104 @@
+use crate::model::{Model, Transform};
+use glm::{rotation, translation, vec4_to_vec3, Mat4, Vec3};
+
+/// Axis of a rotation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RotationAxis {
+    /// Axis in the `direction` through the `pivot` point
+    Fixed { pivot: Vec3, direction: Vec3 },
+    /// Axis through two vertices of the model, where they are before the rotation
+    Line(u32, u32),
+}
+
+/// Rotation of the whole model around an axis
+pub struct Rotate {
+    axis: RotationAxis,
+    angle: f32,
+}
+
+impl Rotate {
+    pub fn new(axis: RotationAxis, angle: f32) -> Self {
+        Self { axis, angle }
+    }
+}
+
+impl Transform for Rotate {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        let mut vertices = model.vertices;
+        let (pivot, direction) = match self.axis {
+            RotationAxis::Fixed { pivot, direction } => (pivot, direction),
+            RotationAxis::Line(a, b) => {
+                let pivot = vertices[a as usize];
+                (pivot, vertices[b as usize] - pivot)
+            }
+        };
+        let transformation: Mat4 = translation(&pivot)
+            * rotation(self.angle * t, &direction.normalize())
+            * translation(&(-pivot));
+
+        for vertex in vertices.iter_mut() {
+            *vertex = vec4_to_vec3(&(transformation * vertex.push(1.0)));
+        }
+
+        Model { vertices, ..model }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Stay;
+    use std::f32::consts::PI;
+
+    fn model() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2)],
+            lines: vec![],
+        }
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(
+            (actual - expected).norm() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rotate_x() {
+        let model = Stay::new().rotate_x(PI * 0.5).apply(model(), 1.0);
+        assert_near(model.vertices[1], Vec3::new(1.0, 0.0, 1.0));
+        let half = Stay::new().rotate_x(PI).apply(self::model(), 0.5);
+        assert_eq!(half.vertices, model.vertices);
+    }
+
+    #[test]
+    fn test_rotate_around_pivot() {
+        let rotate = Rotate::new(
+            RotationAxis::Fixed {
+                pivot: Vec3::new(1.0, 0.0, 0.0),
+                direction: Vec3::new(0.0, 0.0, 2.0),
+            },
+            PI * 0.5,
+        );
+        let model = rotate.apply(model(), 1.0);
+        assert_near(model.vertices[0], Vec3::new(1.0, 0.0, 0.0));
+        assert_near(model.vertices[1], Vec3::new(0.0, 0.0, 0.0));
+        assert_near(model.vertices[2], Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_rotate_around_vertices() {
+        let model = Stay::new().rotate_line((0, 1), PI).apply(model(), 1.0);
+        assert_near(model.vertices[0], Vec3::new(1.0, 0.0, 0.0));
+        assert_near(model.vertices[2], Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(model.triangles, vec![(0, 1, 2)]);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::model::{Model, Transform};
+use glm::Vec3;
+
+/// Scale of the whole model along the axes, from the `center`.
+/// Factors change linearly with `t`, so a negative factor mirrors the model.
+pub struct Scale {
+    factors: Vec3,
+    center: Vec3,
+}
+
+impl Scale {
+    pub fn new(factors: Vec3, center: Vec3) -> Self {
+        Self { factors, center }
+    }
+}
+
+impl Transform for Scale {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        let factors = Vec3::repeat(1.0).lerp(&self.factors, t);
+        let mut vertices = model.vertices;
+        for vertex in vertices.iter_mut() {
+            *vertex = self.center + (*vertex - self.center).component_mul(&factors);
+        }
+        Model { vertices, ..model }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Stay;
+
+    fn model() -> Model {
+        Model {
+            vertices: vec![Vec3::new(1.0, 2.0, 3.0), Vec3::new(-1.0, 0.0, 1.0)],
+            triangles: vec![],
+            lines: vec![],
+        }
+    }
+
+    #[test]
+    fn test_uniform_scale() {
+        let model = Stay::new().scale(3.0).apply(model(), 0.5);
+        assert_eq!(model.vertices[0], Vec3::new(2.0, 4.0, 6.0));
+        assert_eq!(model.vertices[1], Vec3::new(-2.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn test_scale_from_center() {
+        let scale = Scale::new(Vec3::new(2.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 1.0));
+        let model = scale.apply(model(), 1.0);
+        assert_eq!(model.vertices[0], Vec3::new(1.0, 2.0, 1.0));
+        assert_eq!(model.vertices[1], Vec3::new(-3.0, 0.0, 1.0));
+        assert_eq!(
+            scale.apply(self::model(), 0.0).vertices,
+            self::model().vertices
+        );
+    }
+}
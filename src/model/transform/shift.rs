@@ -18,7 +18,7 @@ impl Transform for Shift {
         let shift = self.shift * t;
         let vertices = &mut model.vertices;
         for (i, vertex) in vertices.iter_mut().enumerate() {
-            if self.points.has(i as u8) {
+            if self.points.has(i as u32) {
                 *vertex += shift;
             }
         }
@@ -1,15 +1,21 @@
 use crate::bit_set::BitSet;
 use crate::model::transform::add_lines::AddLines;
+use crate::model::transform::fold::Fold;
 use crate::model::transform::shift::Shift;
 use crate::model::transform::shift_all::ShiftAll;
 use crate::model::transform::transform_parallel::TransformParallel;
-use crate::model::transform::RotateX;
-use crate::model::Model;
-use glm::Vec3;
+use crate::model::transform::{Matrix, Rotate, RotationAxis, Scale};
+use crate::model::{Crease, Model};
+use glm::{Mat4, Vec3};
 
 pub trait Transform {
     fn apply(&self, model: Model, t: f32) -> Model;
 
+    /// Folds done by the transform on the `model`, in the order they are applied
+    fn folds(&self, _model: &Model) -> Vec<Fold> {
+        vec![]
+    }
+
     fn shift_all(self, shift: Vec3) -> TransformParallel<Self, ShiftAll>
     where
         Self: Sized,
@@ -24,17 +30,80 @@ pub trait Transform {
         TransformParallel::new(self, Shift::new(shift, points))
     }
 
-    fn rotate_x(self, angle: f32) -> TransformParallel<Self, RotateX>
+    fn rotate_x(self, angle: f32) -> TransformParallel<Self, Rotate>
+    where
+        Self: Sized,
+    {
+        self.rotate(Vec3::x(), Vec3::zeros(), angle)
+    }
+
+    /// Rotate the whole model around the axis in the `direction` through the `pivot`
+    fn rotate(self, direction: Vec3, pivot: Vec3, angle: f32) -> TransformParallel<Self, Rotate>
+    where
+        Self: Sized,
+    {
+        let axis = RotationAxis::Fixed { pivot, direction };
+        TransformParallel::new(self, Rotate::new(axis, angle))
+    }
+
+    /// Rotate the whole model around the line through two vertices, like a fold of everything
+    fn rotate_line(self, line: (u32, u32), angle: f32) -> TransformParallel<Self, Rotate>
+    where
+        Self: Sized,
+    {
+        let axis = RotationAxis::Line(line.0, line.1);
+        TransformParallel::new(self, Rotate::new(axis, angle))
+    }
+
+    fn scale(self, factor: f32) -> TransformParallel<Self, Scale>
+    where
+        Self: Sized,
+    {
+        self.scale_axes(Vec3::repeat(factor))
+    }
+
+    /// Scale along the axes from the origin
+    fn scale_axes(self, factors: Vec3) -> TransformParallel<Self, Scale>
+    where
+        Self: Sized,
+    {
+        TransformParallel::new(self, Scale::new(factors, Vec3::zeros()))
+    }
+
+    /// Move the whole model by the affine `matrix`, see `Matrix` for the interpolation
+    fn transform(self, matrix: Mat4) -> TransformParallel<Self, Matrix>
     where
         Self: Sized,
     {
-        TransformParallel::new(self, RotateX::new(angle))
+        TransformParallel::new(self, Matrix::new(matrix))
     }
 
-    fn add_lines(self, lines: Vec<(u8, u8)>) -> TransformParallel<Self, AddLines>
+    /// Draw lines of the `crease` type, fading in during the step
+    fn add_lines(self, lines: Vec<(u32, u32)>, crease: Crease) -> TransformParallel<Self, AddLines>
     where
         Self: Sized,
     {
-        TransformParallel::new(self, AddLines::new(lines))
+        TransformParallel::new(self, AddLines::new(lines, crease))
+    }
+}
+
+/// Transforms applied one after another in the same step
+impl Transform for Vec<Box<dyn Transform>> {
+    fn apply(&self, model: Model, t: f32) -> Model {
+        self.iter()
+            .fold(model, |model, transform| transform.apply(model, t))
+    }
+
+    /// Folds of every transform on the model the earlier ones are done on
+    fn folds(&self, model: &Model) -> Vec<Fold> {
+        let mut folds = Vec::new();
+        let mut model = model.clone();
+        for (i, transform) in self.iter().enumerate() {
+            folds.extend(transform.folds(&model));
+            if i + 1 < self.len() {
+                model = transform.apply(model, 1.0);
+            }
+        }
+        folds
     }
 }
@@ -1,3 +1,4 @@
+use crate::model::transform::fold::Fold;
 use crate::model::transform::Transform;
 use crate::model::Model;
 
@@ -17,4 +18,10 @@ impl<T1: Transform, T2: Transform> Transform for TransformParallel<T1, T2> {
         let model = self.t1.apply(model, t);
         self.t2.apply(model, t)
     }
+
+    fn folds(&self, model: &Model) -> Vec<Fold> {
+        let mut folds = self.t1.folds(model);
+        folds.extend(self.t2.folds(&self.t1.apply(model.clone(), 1.0)));
+        folds
+    }
 }
@@ -0,0 +1,332 @@
+use crate::model::fold_file::{EdgeAssignment, FoldPattern};
+use crate::model::Model;
+use glm::Vec3;
+use spade::handles::DirectedEdgeHandle;
+use spade::{CdtEdge, ConstrainedDelaunayTriangulation, InsertionError, Point2, Triangulation};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Vertices closer to a crease than this part of its length are split points of the crease
+const ON_LINE_TOLERANCE: f32 = 1e-5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriangulationError {
+    NotFlat {
+        vertex: usize,
+    },
+    InvalidPosition {
+        vertex: usize,
+    },
+    /// Vertex at the same point as an earlier one, the triangulation would join them
+    /// and shift the indices of the later vertices
+    DuplicateVertex {
+        vertex: usize,
+        first: usize,
+    },
+}
+
+impl Display for TriangulationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TriangulationError::NotFlat { vertex } => {
+                write!(f, "vertex {} is out of the z = 0 plane", vertex)
+            }
+            TriangulationError::InvalidPosition { vertex } => {
+                write!(f, "vertex {} has an invalid position", vertex)
+            }
+            TriangulationError::DuplicateVertex { vertex, first } => {
+                write!(
+                    f,
+                    "vertex {} is at the same point as vertex {}",
+                    vertex, first
+                )
+            }
+        }
+    }
+}
+
+impl Error for TriangulationError {}
+
+impl FoldPattern {
+    /// Replace triangles with a constrained Delaunay triangulation of the flat pattern,
+    /// so every crease line is a triangle edge. Crossing lines are split at new vertices,
+    /// which are added after the existing ones. Only triangles enclosed by border lines
+    /// are kept, or the whole convex hull when there are no border lines.
+    #[allow(dead_code)]
+    pub fn triangulate(&self) -> Result<FoldPattern, TriangulationError> {
+        let model = &self.model;
+        let mut cdt: ConstrainedDelaunayTriangulation<Point2<f64>> =
+            ConstrainedDelaunayTriangulation::new();
+
+        let mut handles = Vec::with_capacity(model.vertices.len());
+        for (i, v) in model.vertices.iter().enumerate() {
+            if v.z != 0.0 {
+                return Err(TriangulationError::NotFlat { vertex: i });
+            }
+            let handle = cdt
+                .insert(Point2::new(v.x as f64, v.y as f64))
+                .map_err(|_: InsertionError| TriangulationError::InvalidPosition { vertex: i })?;
+            if handle.index() != i {
+                return Err(TriangulationError::DuplicateVertex {
+                    vertex: i,
+                    first: handle.index(),
+                });
+            }
+            handles.push(handle);
+        }
+
+        for &(a, b, _, _) in model.lines.iter() {
+            let (from, to) = (handles[a as usize], handles[b as usize]);
+            if from != to {
+                cdt.add_constraint_and_split(from, to, |p| p);
+            }
+        }
+
+        let vertices: Vec<Vec3> = cdt
+            .vertices()
+            .map(|v| Vec3::new(v.position().x as f32, v.position().y as f32, 0.0))
+            .collect();
+
+        // Later constraints can split earlier ones, so lines are built from
+        // the vertices that ended up on every segment
+        let mut lines = Vec::new();
+        let mut assignments = Vec::new();
+        for (&(a, b, alpha, crease), &assignment) in model.lines.iter().zip(self.assignments.iter())
+        {
+            let (from, to) = (handles[a as usize], handles[b as usize]);
+            let (p0, p1) = (vertices[from.index()].xy(), vertices[to.index()].xy());
+            if from == to {
+                continue;
+            }
+            let direction = p1 - p0;
+            let length = direction.norm();
+            let tolerance = ON_LINE_TOLERANCE * length;
+            let mut points: Vec<(f32, u32)> = vertices
+                .iter()
+                .enumerate()
+                .filter_map(|(i, v)| {
+                    let p = v.xy();
+                    let t = (p - p0).dot(&direction) / (length * length);
+                    let distance = direction.perp(&(p - p0)).abs() / length;
+                    let on_line = i == from.index()
+                        || i == to.index()
+                        || (t > 0.0 && t < 1.0 && distance <= tolerance);
+                    if on_line {
+                        Some((t, i as u32))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            points.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+            for pair in points.windows(2) {
+                lines.push((pair[0].1, pair[1].1, alpha, crease));
+                assignments.push(assignment);
+            }
+        }
+
+        let border: HashSet<(usize, usize)> = lines
+            .iter()
+            .zip(assignments.iter())
+            .filter(|(_, &assignment)| assignment == EdgeAssignment::Border)
+            .map(|(&(a, b, _, _), _)| (a.min(b) as usize, a.max(b) as usize))
+            .collect();
+        let is_border = |edge: DirectedEdgeHandle<Point2<f64>, (), CdtEdge<()>, ()>| {
+            let (a, b) = (edge.from().fix().index(), edge.to().fix().index());
+            border.contains(&(a.min(b), a.max(b)))
+        };
+
+        // Faces reachable from the outside without crossing a border are cut off
+        let mut outside = vec![false; cdt.num_all_faces()];
+        let mut queue: Vec<_> = cdt
+            .inner_faces()
+            .filter(|face| {
+                !border.is_empty()
+                    && face
+                        .adjacent_edges()
+                        .iter()
+                        .any(|&edge| edge.rev().face().is_outer() && !is_border(edge))
+            })
+            .collect();
+        for face in queue.iter() {
+            outside[face.fix().index()] = true;
+        }
+        while let Some(face) = queue.pop() {
+            for &edge in face.adjacent_edges().iter() {
+                if let Some(next) = edge.rev().face().as_inner() {
+                    if !outside[next.fix().index()] && !is_border(edge) {
+                        outside[next.fix().index()] = true;
+                        queue.push(next);
+                    }
+                }
+            }
+        }
+
+        let triangles = cdt
+            .inner_faces()
+            .filter(|face| !outside[face.fix().index()])
+            .map(|face| {
+                let [a, b, c] = face.vertices();
+                (
+                    a.fix().index() as u32,
+                    b.fix().index() as u32,
+                    c.fix().index() as u32,
+                )
+            })
+            .collect();
+
+        Ok(FoldPattern {
+            model: Model {
+                vertices,
+                triangles,
+                lines,
+            },
+            assignments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Crease;
+
+    fn edges(model: &Model) -> HashSet<(u32, u32)> {
+        model
+            .triangles
+            .iter()
+            .flat_map(|&(a, b, c)| vec![(a, b), (b, c), (c, a)])
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect()
+    }
+
+    fn area(model: &Model) -> f32 {
+        model
+            .triangles
+            .iter()
+            .map(|&(a, b, c)| {
+                let v = |i: u32| model.vertices[i as usize];
+                (v(b) - v(a)).cross(&(v(c) - v(a))).z * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_triangulate_square() {
+        let pattern = FoldPattern::from_svg(
+            r#"<svg>
+                <rect x="0" y="0" width="4" height="4"/>
+                <line class="valley" x1="0" y1="0" x2="4" y2="4"/>
+                <line class="mountain" x1="0" y1="4" x2="2" y2="2"/>
+                <line class="mountain" x1="1" y1="3" x2="3" y2="3"/>
+            </svg>"#,
+            1e-4,
+        )
+        .unwrap()
+        .triangulate()
+        .unwrap();
+
+        let model = &pattern.model;
+        assert!((area(model) - 16.0).abs() < 1e-4);
+        // Counter-clockwise in the model coordinates, as the plane triangles
+        assert!(model.triangles.iter().all(|&(a, b, c)| {
+            let v = |i: u32| model.vertices[i as usize];
+            (v(b) - v(a)).cross(&(v(c) - v(a))).z > 0.0
+        }));
+        let edges = edges(model);
+        for &(a, b, _, _) in model.lines.iter() {
+            assert!(edges.contains(&(a.min(b), a.max(b))));
+        }
+    }
+
+    #[test]
+    fn test_triangulate_concave_outline() {
+        let pattern = FoldPattern::from_svg(
+            r#"<svg><path class="border" d="M 0 0 L 4 0 L 4 4 L 2 1 L 0 4 Z"/></svg>"#,
+            1e-4,
+        )
+        .unwrap()
+        .triangulate()
+        .unwrap();
+        assert_eq!(pattern.model.triangles.len(), 3);
+        assert!((area(&pattern.model) - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_split_crossing_creases() {
+        let pattern = FoldPattern {
+            model: Model {
+                vertices: vec![
+                    Vec3::new(0.0, 0.0, 0.0),
+                    Vec3::new(2.0, 2.0, 0.0),
+                    Vec3::new(2.0, 0.0, 0.0),
+                    Vec3::new(0.0, 2.0, 0.0),
+                ],
+                triangles: vec![],
+                lines: vec![(0, 1, 1.0, Crease::Valley), (2, 3, 1.0, Crease::Mountain)],
+            },
+            assignments: vec![EdgeAssignment::Valley, EdgeAssignment::Mountain],
+        }
+        .triangulate()
+        .unwrap();
+
+        assert_eq!(pattern.model.vertices.len(), 5);
+        assert_eq!(pattern.model.vertices[4], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(pattern.model.lines.len(), 4);
+        assert_eq!(pattern.model.triangles.len(), 4);
+    }
+
+    #[test]
+    fn test_triangulate_bend_lines() {
+        let pattern = FoldPattern::from_svg(include_str!("../../design/bend-lines.svg"), 0.5)
+            .unwrap()
+            .triangulate()
+            .unwrap();
+        assert!((area(&pattern.model) / (210.0 * 297.0) - 1.0).abs() < 0.01);
+        let edges = edges(&pattern.model);
+        for &(a, b, _, _) in pattern.model.lines.iter() {
+            assert!(edges.contains(&(a.min(b), a.max(b))));
+        }
+    }
+
+    #[test]
+    fn test_not_flat() {
+        let pattern = FoldPattern {
+            model: Model {
+                vertices: vec![Vec3::new(0.0, 0.0, 1.0)],
+                triangles: vec![],
+                lines: vec![],
+            },
+            assignments: vec![],
+        };
+        assert_eq!(
+            pattern.triangulate().unwrap_err(),
+            TriangulationError::NotFlat { vertex: 0 }
+        );
+    }
+
+    #[test]
+    fn test_duplicate_vertex() {
+        let pattern = FoldPattern {
+            model: Model {
+                vertices: vec![
+                    Vec3::new(0.0, 0.0, 0.0),
+                    Vec3::new(1.0, 0.0, 0.0),
+                    Vec3::new(0.0, 0.0, 0.0),
+                    Vec3::new(0.0, 1.0, 0.0),
+                ],
+                triangles: vec![],
+                lines: vec![],
+            },
+            assignments: vec![],
+        };
+        assert_eq!(
+            pattern.triangulate().unwrap_err(),
+            TriangulationError::DuplicateVertex {
+                vertex: 2,
+                first: 0
+            }
+        );
+    }
+}
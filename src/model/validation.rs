@@ -0,0 +1,306 @@
+use crate::model::Model;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Triangles with the area smaller than this part of the squared longest edge are degenerate
+const MIN_RELATIVE_AREA: f32 = 1e-6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    TriangleIndexOutOfRange {
+        triangle: usize,
+        vertex: u32,
+    },
+    LineIndexOutOfRange {
+        line: usize,
+        vertex: u32,
+    },
+    DegenerateTriangle {
+        triangle: usize,
+        area: f32,
+    },
+    DuplicateTriangle {
+        triangle: usize,
+        duplicate_of: usize,
+    },
+    /// Two triangles go along their shared edge in the same direction
+    InconsistentWinding {
+        edge: (u32, u32),
+        triangles: (usize, usize),
+    },
+    /// Edge shared by more than two triangles
+    NonManifoldEdge {
+        edge: (u32, u32),
+        triangles: Vec<usize>,
+    },
+    LineNotTriangleEdge {
+        line: usize,
+    },
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::TriangleIndexOutOfRange { triangle, vertex } => {
+                write!(
+                    f,
+                    "triangle {} refers to missing vertex {}",
+                    triangle, vertex
+                )
+            }
+            Issue::LineIndexOutOfRange { line, vertex } => {
+                write!(f, "line {} refers to missing vertex {}", line, vertex)
+            }
+            Issue::DegenerateTriangle { triangle, area } => {
+                write!(f, "triangle {} is degenerate, area {}", triangle, area)
+            }
+            Issue::DuplicateTriangle {
+                triangle,
+                duplicate_of,
+            } => write!(
+                f,
+                "triangle {} duplicates triangle {}",
+                triangle, duplicate_of
+            ),
+            Issue::InconsistentWinding { edge, triangles } => write!(
+                f,
+                "triangles {} and {} have opposite winding at edge {:?}",
+                triangles.0, triangles.1, edge
+            ),
+            Issue::NonManifoldEdge { edge, triangles } => {
+                write!(f, "edge {:?} is shared by triangles {:?}", edge, triangles)
+            }
+            Issue::LineNotTriangleEdge { line } => {
+                write!(f, "line {} is not a triangle edge", line)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    #[allow(dead_code)]
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "model is valid");
+        }
+        for issue in self.issues.iter() {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl Model {
+    /// Check the mesh for errors that only show up as glitches when it's rendered
+    #[allow(dead_code)]
+    pub fn validate(&self) -> ValidationReport {
+        let mut issues = Vec::new();
+        let count = self.vertices.len() as u32;
+
+        let mut valid_triangles = Vec::new();
+        for (i, &(a, b, c)) in self.triangles.iter().enumerate() {
+            let missing: Vec<u32> = [a, b, c].iter().copied().filter(|&v| v >= count).collect();
+            for &vertex in missing.iter() {
+                issues.push(Issue::TriangleIndexOutOfRange {
+                    triangle: i,
+                    vertex,
+                });
+            }
+            if missing.is_empty() {
+                valid_triangles.push(i);
+            }
+        }
+        for (i, &(a, b, _, _)) in self.lines.iter().enumerate() {
+            for &vertex in [a, b].iter().filter(|&&v| v >= count) {
+                issues.push(Issue::LineIndexOutOfRange { line: i, vertex });
+            }
+        }
+
+        let mut sorted: HashMap<[u32; 3], usize> = HashMap::new();
+        // Undirected edge to triangles having it, with the edge direction in the triangle
+        let mut edges: HashMap<(u32, u32), Vec<(usize, bool)>> = HashMap::new();
+        for &i in valid_triangles.iter() {
+            let (a, b, c) = self.triangles[i];
+            let v = |i: u32| self.vertices[i as usize];
+
+            let longest = [(a, b), (b, c), (c, a)]
+                .iter()
+                .map(|&(p, q)| (v(q) - v(p)).norm_squared())
+                .fold(0.0, f32::max);
+            let area = (v(b) - v(a)).cross(&(v(c) - v(a))).norm() * 0.5;
+            let repeated = a == b || b == c || c == a;
+            if repeated || area <= longest * MIN_RELATIVE_AREA {
+                issues.push(Issue::DegenerateTriangle { triangle: i, area });
+            }
+            if repeated {
+                continue;
+            }
+
+            let mut key = [a, b, c];
+            key.sort_unstable();
+            match sorted.get(&key) {
+                Some(&duplicate_of) => issues.push(Issue::DuplicateTriangle {
+                    triangle: i,
+                    duplicate_of,
+                }),
+                None => {
+                    sorted.insert(key, i);
+                }
+            }
+
+            for &(p, q) in [(a, b), (b, c), (c, a)].iter() {
+                edges
+                    .entry((p.min(q), p.max(q)))
+                    .or_default()
+                    .push((i, p < q));
+            }
+        }
+
+        let mut shared: Vec<_> = edges.iter().filter(|(_, t)| t.len() > 1).collect();
+        shared.sort_by_key(|(&edge, _)| edge);
+        for (&edge, triangles) in shared {
+            if triangles.len() > 2 {
+                issues.push(Issue::NonManifoldEdge {
+                    edge,
+                    triangles: triangles.iter().map(|&(i, _)| i).collect(),
+                });
+            } else if triangles[0].1 == triangles[1].1 {
+                issues.push(Issue::InconsistentWinding {
+                    edge,
+                    triangles: (triangles[0].0, triangles[1].0),
+                });
+            }
+        }
+
+        for (i, &(a, b, _, _)) in self.lines.iter().enumerate() {
+            if a < count && b < count && !edges.contains_key(&(a.min(b), a.max(b))) {
+                issues.push(Issue::LineNotTriangleEdge { line: i });
+            }
+        }
+
+        ValidationReport { issues }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Crease;
+    use glm::Vec3;
+
+    fn square() -> Model {
+        Model {
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![(0, 1, 2), (0, 2, 3)],
+            lines: vec![(0, 2, 1.0, Crease::Valley), (0, 1, 1.0, Crease::Border)],
+        }
+    }
+
+    #[test]
+    fn test_valid() {
+        let report = square().validate();
+        assert!(report.is_valid(), "{}", report);
+    }
+
+    #[test]
+    fn test_index_out_of_range() {
+        let mut model = square();
+        model.triangles.push((0, 1, 7));
+        model.lines.push((0, 9, 1.0, Crease::Guide));
+        assert_eq!(
+            model.validate().issues,
+            vec![
+                Issue::TriangleIndexOutOfRange {
+                    triangle: 2,
+                    vertex: 7
+                },
+                Issue::LineIndexOutOfRange { line: 2, vertex: 9 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_degenerate_triangles() {
+        let mut model = square();
+        model.vertices.push(Vec3::new(2.0, 2.0, 0.0));
+        model.vertices.push(Vec3::new(3.0, 3.0, 0.0));
+        model.triangles.push((2, 4, 5));
+        model.triangles.push((1, 1, 2));
+        assert_eq!(
+            model.validate().issues,
+            vec![
+                Issue::DegenerateTriangle {
+                    triangle: 2,
+                    area: 0.0
+                },
+                Issue::DegenerateTriangle {
+                    triangle: 3,
+                    area: 0.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_triangles() {
+        let mut model = square();
+        model.triangles.push((1, 2, 0));
+        assert!(model.validate().issues.contains(&Issue::DuplicateTriangle {
+            triangle: 2,
+            duplicate_of: 0
+        }));
+    }
+
+    #[test]
+    fn test_inconsistent_winding() {
+        let mut model = square();
+        model.triangles[1] = (0, 3, 2);
+        assert_eq!(
+            model.validate().issues,
+            vec![Issue::InconsistentWinding {
+                edge: (0, 2),
+                triangles: (0, 1)
+            }]
+        );
+    }
+
+    #[test]
+    fn test_non_manifold_edge() {
+        let mut model = square();
+        model.vertices.push(Vec3::new(0.5, 0.5, 1.0));
+        model.triangles.push((0, 4, 2));
+        assert_eq!(
+            model.validate().issues,
+            vec![Issue::NonManifoldEdge {
+                edge: (0, 2),
+                triangles: vec![0, 1, 2]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_line_not_triangle_edge() {
+        let mut model = square();
+        model.lines.push((1, 3, 1.0, Crease::Guide));
+        assert_eq!(
+            model.validate().issues,
+            vec![Issue::LineNotTriangleEdge { line: 2 }]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::background::Background;
+use crate::caption::Caption;
 use crate::cover::Cover;
 use crate::flight::Flight;
 use crate::floor::Floor;
@@ -6,7 +7,7 @@ use crate::plane_geometry::PlaneGeometry;
 use crate::plane_program::PlaneProgram;
 use crate::scene::Scene;
 use glm::Vec3;
-use web_sys::HtmlCanvasElement;
+use web_sys::{HtmlCanvasElement, HtmlElement};
 use webgl_rc::{Gl, GlError, Settings};
 
 const LIGHT_POSITION: Vec3 = Vec3::new(3.0, 0.5, -3.0);
@@ -19,17 +20,19 @@ pub struct Plane {
     background: Background,
     floor: Floor,
     flight: Flight,
+    caption: Caption,
 }
 
 impl Plane {
-    pub fn new(canvas: &HtmlCanvasElement) -> Result<Plane, GlError> {
+    pub fn new(canvas: &HtmlCanvasElement, caption: HtmlElement) -> Result<Plane, GlError> {
         let plane_geometry = PlaneGeometry::new();
         let gl = Gl::new(canvas)?;
         let plane_program = PlaneProgram::new(gl.clone())?;
         let cover = Cover::new(gl.clone())?;
         let background = Background::new(gl.clone())?;
         let floor = Floor::new(gl.clone())?;
-        let flight = Flight::new();
+        let flight = Flight::new(plane_geometry.timeline());
+        let caption = Caption::new(caption);
 
         Ok(Plane {
             gl,
@@ -39,6 +42,7 @@ impl Plane {
             background,
             floor,
             flight,
+            caption,
         })
     }
 
@@ -60,10 +64,14 @@ impl Plane {
                 self.background.render();
                 self.floor
                     .render(scene.camera.get_projection_matrix() * scene.camera.get_view_matrix());
-                self.plane_program
-                    .draw(&scene, &self.plane_geometry.get_model(frame.fold_phase));
+                self.plane_program.draw(
+                    &scene,
+                    &self.plane_geometry.get_model(frame.fold_phase),
+                    self.plane_geometry.get_face_offsets(frame.fold_phase),
+                );
                 self.cover.render(frame.cover_opacity);
             },
         );
+        self.caption.show(self.flight.step(&frame));
     }
 }
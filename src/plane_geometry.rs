@@ -1,13 +1,19 @@
 use crate::model::transform::Transform;
 use std::f32::consts::PI;
 
-use crate::bit_set::BitSet;
-use crate::model::transform::fold::Fold;
-use crate::model::{AnimatedModel, Model, Stay};
-use glm::Vec3;
+use crate::model::baked::Baked;
+use crate::model::svg_file::{crease_pattern, SvgFileError};
+use crate::model::timeline::Timeline;
+use crate::model::transform::fold::{Fold, FoldSide};
+use crate::model::{AnimatedModel, Crease, Model, Stay, Symmetry};
+use glm::{scaling, translation, Vec2, Vec3};
 
 const SCALE: f32 = 1.0 / 297.0;
-const FOLD_FACTOR: f32 = 0.97;
+const WELD_TOLERANCE: f32 = 1e-5;
+/// Distance between stacked layers of paper, about 0.3 mm
+const LAYER_GAP: f32 = 0.001;
+/// Samples of the baked animation, rotations between them are straightened slightly
+const SAMPLES_PER_SECOND: f32 = 60.0;
 
 static POINTS: &[(f32, f32)] = &[
     (0.0, 0.0),
@@ -33,7 +39,7 @@ static POINTS: &[(f32, f32)] = &[
     (105.0, 297.0),
 ];
 
-static INDEXES: &[(u8, u8, u8)] = &[
+static INDEXES: &[(u32, u32, u32)] = &[
     (0, 8, 9),
     (0, 9, 10),
     (8, 13, 9),
@@ -74,122 +80,314 @@ fn create_static_plane() -> Model {
     }
 }
 
+/// Fold of the part connected to the `side` vertex in the final state of the model
+fn fold(model: &impl AnimatedModel, line: (u32, u32), side: u32, angle: f32) -> Fold {
+    let current = model.get_model(model.duration());
+    Fold::with_side(&current, line, FoldSide::Component(side), angle)
+        .expect("plane folds don't tear faces")
+}
+
 fn create_animated_plane() -> impl AnimatedModel {
     let model = create_static_plane();
-    let model = model.animate(
-        1.0,
-        Fold::new(
-            (0, 4),
-            BitSet::with_bits(&[2, 3, 5, 6, 7, 8, 9, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20]),
-            PI * 0.5 * FOLD_FACTOR,
-        )
-        .add_lines(vec![
-            (0, 10),
-            (10, 1),
-            (1, 4),
-            (0, 8),
-            (8, 2),
-            (4, 12),
-            (12, 20),
-            (2, 14),
-            (14, 19),
-            (19, 18),
-            (18, 3),
-            (3, 16),
-            (16, 6),
-            (6, 20),
-        ]),
-    );
-    let model = model.animate(
-        1.0,
-        Fold::new(
-            (0, 4),
-            BitSet::with_bits(&[
-                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
-            ]),
-            -PI * 0.5 * FOLD_FACTOR,
-        ),
-    );
-    let model = model.animate(
-        1.0,
-        Fold::new((0, 3), BitSet::with_bits(&[8, 2, 14, 19, 18]), PI).add_lines(vec![
-            (0, 9),
-            (9, 13),
-            (13, 7),
-            (7, 17),
-            (17, 3),
-        ]),
-    );
-    let model = model.animate(
-        1.0,
-        Fold::new(
-            (1, 3),
-            BitSet::with_bits(&[0, 8, 2, 14, 19, 18, 9, 13, 7, 17, 10]),
-            PI,
-        )
-        .add_lines(vec![(1, 11), (11, 5), (5, 15), (15, 3)]),
-    );
-    let model = model.animate(
-        0.25,
-        Stay::new().shift_all(Vec3::new(0.0, -210.0 * 0.5 * 0.5 / 297.0, 0.0)),
-    );
-    let model = model.animate(
-        1.0,
-        Fold::new((5, 6), BitSet::with_bits(&[18, 17, 3, 15, 16]), PI).add_lines(vec![
-            (5, 6),
-            (5, 7),
-            (7, 19),
-        ]),
-    );
-    let model = model.animate(
-        1.0,
-        Fold::new((9, 10), BitSet::with_bits(&[0]), PI).add_lines(vec![(9, 10), (9, 8)]),
-    );
-    let model = model.animate(
-        1.0,
-        Fold::new(
-            (1, 4),
-            BitSet::with_bits(&[2, 3, 5, 6, 7, 8, 9, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20]),
-            -PI * 0.5 * FOLD_FACTOR,
+    let step = fold(&model, (0, 4), 2, PI * 0.5);
+    let model = model.animate(1.0, step).label("Crease the centre line");
+    let step = fold(&model, (0, 4), 2, -PI * 0.5);
+    let model = model.animate(1.0, step).label("Unfold");
+    let step = fold(&model, (0, 3), 2, PI);
+    let model = model
+        .animate(1.0, step)
+        .label("Fold the corners to the centre line");
+    let step = fold(&model, (1, 3), 0, PI);
+    let model = model.animate(1.0, step).label("Fold the top down");
+    let model = model
+        .animate(
+            0.25,
+            Stay::new().shift_all(Vec3::new(0.0, -210.0 * 0.5 * 0.5 / 297.0, 0.0)),
         )
-        .shift(Vec3::new(0.0, 0.0, -0.003), BitSet::with_bits(&[0])),
-    );
-    let model = model.animate(
-        1.0,
-        Fold::new((11, 12), BitSet::with_bits(&[5, 7, 19, 6, 20]), PI * 0.5)
-            .add_lines(vec![
-                (11, 12),
-                (11, 13),
-                (13, 14),
-                (15, 16),
-                (15, 17),
-                (17, 18),
-            ])
-            .shift(Vec3::new(0.0, 0.0, 0.015), BitSet::with_bits(&[4, 12, 20]))
-            .rotate_x(-PI * 0.08),
-    );
+        .label("Centre the sheet");
+    let step = fold(&model, (5, 6), 16, PI);
+    let model = model
+        .animate(1.0, step)
+        .label("Fold the edges to the centre line");
+    let step = fold(&model, (9, 10), 0, PI);
+    let model = model.animate(1.0, step).label("Fold the nose tip back");
+    let step = fold(&model, (1, 4), 20, -PI * 0.5);
+    let model = model.animate(1.0, step).label("Fold in half");
+    let step = fold(&model, (11, 12), 20, PI * 0.5).rotate_x(-PI * 0.08);
+    let model = model.animate(1.0, step).label("Fold the wings down");
 
     model
 }
 
+/// Lines and face offsets of one baked sample of the plane
+struct Sample {
+    /// Lines with the creases found on the whole plane, so the seam gets one
+    /// where the halves meet
+    lines: Vec<(u32, u32, f32, Crease)>,
+    offsets: Vec<Vec3>,
+}
+
 pub struct PlaneGeometry {
-    model: Box<dyn AnimatedModel>,
+    /// Right half of the plane with its mirror image, baked for playback
+    model: Baked,
+    /// Copies of the half, the first one is the right half
+    copies: usize,
+    samples: Vec<Sample>,
 }
 
 /// Animated foldable plane geometry
 impl PlaneGeometry {
     pub fn new() -> PlaneGeometry {
-        PlaneGeometry {
-            model: Box::new(create_animated_plane()),
-        }
+        let model = create_animated_plane().symmetric(Symmetry::mirror_x(), WELD_TOLERANCE);
+        let samples = (model.duration() * SAMPLES_PER_SECOND).ceil() as usize;
+        let mut geometry = PlaneGeometry {
+            model: model.bake(samples).expect("plane folds keep the triangles"),
+            copies: model.copies().len(),
+            samples: vec![],
+        };
+        geometry.samples = (0..=samples)
+            .map(|i| {
+                let time = geometry.model.duration() * i as f32 / samples as f32;
+                let model = geometry.model.get_model(time);
+                let offsets = geometry.face_offsets(&model, time);
+                Sample {
+                    lines: model.lines_with_creases(&offsets),
+                    offsets,
+                }
+            })
+            .collect();
+        geometry
     }
 
-    /// Get points for time from 0.0 to 1.0
+    /// The baked sample nearest to the phase `t`
+    fn sample(&self, t: f32) -> &Sample {
+        let time = t.clamp(0.0, 1.0) * self.model.duration();
+        &self.samples[self.model.nearest_sample(time)]
+    }
+
+    /// Get points for time from 0.0 to 1.0, with the lines of the nearest sample
     pub fn get_model(&self, t: f32) -> Model {
         let time = t.clamp(0.0, 1.0) * self.model.duration();
-        let model = self.model.get_model(time);
-        let model = model.clone().merge(model.flip_x());
+        Model {
+            vertices: self.model.vertices(time),
+            triangles: self.model.triangles().to_vec(),
+            lines: self.sample(t).lines.clone(),
+        }
+    }
+
+    /// Steps of the folding in phases from 0.0 to 1.0, see `get_model`
+    pub fn timeline(&self) -> Timeline {
+        self.model.timeline().scaled(1.0 / self.model.duration())
+    }
+
+    /// Offsets of the triangles of the model at time `t` that move stacked layers
+    /// of paper apart, from the nearest sample
+    pub fn get_face_offsets(&self, t: f32) -> &[Vec3] {
+        &self.sample(t).offsets
+    }
+
+    /// Offsets of the triangles of the `model` at `time`, see `get_face_offsets`.
+    /// The right half comes to the symmetry plane from the positive side,
+    /// so its faces lying on the plane are kept on that side, and the mirrored ones
+    /// on the other side.
+    fn face_offsets(&self, model: &Model, time: f32) -> Vec<Vec3> {
+        let mut offsets = self.model.layers(time).offsets(model, LAYER_GAP);
+
+        // Faces of the mirrored half follow the faces of the right half
+        let half = model.triangles.len() / self.copies;
+        let on_symmetry_plane: Vec<usize> = (0..model.triangles.len())
+            .filter(|&face| {
+                let (a, b, c) = model.triangles[face];
+                [a, b, c]
+                    .iter()
+                    .all(|&i| model.vertices[i as usize].x.abs() <= WELD_TOLERANCE)
+            })
+            .collect();
+        let closest = on_symmetry_plane
+            .iter()
+            .filter(|&&face| face < half)
+            .map(|&face| offsets[face].x)
+            .fold(f32::INFINITY, f32::min);
+        let shift = LAYER_GAP * 0.5 - closest;
+        if shift > 0.0 {
+            for &face in on_symmetry_plane.iter() {
+                offsets[face].x += if face < half { shift } else { -shift };
+            }
+        }
+        offsets
+    }
+
+    /// Printable crease pattern of the plane on an A4 sheet, in millimetres
+    #[allow(dead_code)]
+    pub fn crease_pattern_svg(&self) -> Result<String, SvgFileError> {
+        let paper = Vec2::new(210.0, 297.0);
+        let to_sheet = |flip: f32| {
+            translation(&Vec3::new(paper.x * 0.5, 0.0, 0.0))
+                * scaling(&Vec3::new(flip / SCALE, 1.0 / SCALE, 1.0))
+                * translation(&Vec3::new(0.0, 0.5, 0.0))
+        };
+        // The baked animation has no fold steps, so they are taken from the half
+        crease_pattern(
+            &create_animated_plane(),
+            paper,
+            &[to_sheet(1.0), to_sheet(-1.0)],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::fold_file::{export, EdgeAssignment, FoldPattern};
+    use crate::model::fold_script::parse;
+    use crate::model::intersection::check_intersections;
+    use crate::model::layers::stacked;
+    use crate::model::Crease;
+
+    #[test]
+    fn test_seam_is_welded_in_every_phase() {
+        let geometry = PlaneGeometry::new();
+        for i in 0..=20 {
+            let model = geometry.get_model(i as f32 / 20.0);
+            assert_eq!(model.vertices.len(), POINTS.len() * 2 - 4);
+            assert_eq!(model.triangles.len(), INDEXES.len() * 2);
+        }
+    }
+
+    #[test]
+    fn test_baked_close_to_animation() {
+        let geometry = PlaneGeometry::new();
+        let exact = create_animated_plane().symmetric(Symmetry::mirror_x(), WELD_TOLERANCE);
+        for i in 0..=40 {
+            let t = i as f32 / 40.0 + 0.003;
+            let expected = exact.get_model(t.min(1.0) * exact.duration());
+            let actual = geometry.get_model(t);
+            assert_eq!(actual.triangles, expected.triangles);
+            for (a, b) in actual.vertices.iter().zip(expected.vertices.iter()) {
+                assert!((a - b).norm() < 1e-3, "phase {}: {} != {}", t, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_valid_in_every_phase() {
+        let geometry = PlaneGeometry::new();
+        for i in 0..=20 {
+            let t = i as f32 / 20.0;
+            let report = geometry.get_model(t).validate();
+            assert!(report.is_valid(), "phase {}: {}", t, report);
+        }
+    }
+
+    #[test]
+    fn test_layers_apart_in_every_phase() {
+        let geometry = PlaneGeometry::new();
+        for i in 0..=40 {
+            let t = i as f32 / 40.0;
+            let model = geometry.get_model(t);
+            let offsets = geometry.get_face_offsets(t);
+            assert_eq!(offsets.len(), model.triangles.len());
+            // Every face with its own vertices, as they are drawn
+            let separated = Model {
+                vertices: model
+                    .triangles
+                    .iter()
+                    .zip(offsets.iter())
+                    .flat_map(|(&(a, b, c), offset)| {
+                        let v = |i: u32| model.vertices[i as usize] + offset;
+                        vec![v(a), v(b), v(c)]
+                    })
+                    .collect(),
+                triangles: (0..model.triangles.len() as u32)
+                    .map(|i| (i * 3, i * 3 + 1, i * 3 + 2))
+                    .collect(),
+                lines: vec![],
+            };
+            let stacked = stacked(&separated);
+            assert!(stacked.is_empty(), "phase {}: {:?}", t, stacked);
+        }
+    }
+
+    #[test]
+    fn test_crease_lines() {
+        let geometry = PlaneGeometry::new();
+        let flat = geometry.get_model(0.0);
+        assert!(!flat.lines.is_empty());
+        assert!(flat
+            .lines
+            .iter()
+            .all(|&(_, _, opacity, crease)| opacity == 1.0 && crease == Crease::Border));
+
+        let folded = geometry.get_model(1.0);
+        let crease = |a: u32, b: u32| {
+            folded
+                .lines
+                .iter()
+                .find(|&&(x, y, _, _)| (x, y) == (a.min(b), a.max(b)))
+                .map(|&(_, _, _, crease)| crease)
+        };
+        assert_eq!(crease(9, 10), Some(Crease::Valley));
+        assert_eq!(crease(8, 9), Some(Crease::Mountain));
+        assert_eq!(crease(5, 7), Some(Crease::Valley));
+        assert_eq!(crease(5, 6), Some(Crease::Mountain));
+        assert_eq!(crease(0, 8), Some(Crease::Border));
+    }
+
+    #[test]
+    fn test_folds_dont_intersect() {
+        let report = check_intersections(&create_animated_plane(), 200);
+        assert!(report.is_clean(), "{}", report);
+    }
+
+    #[test]
+    fn test_fold_script() {
+        let script = include_str!("../design/plane.folds");
+        let scripted = parse(script, create_static_plane()).unwrap();
+        let model = create_animated_plane();
+        assert_eq!(scripted.duration(), model.duration());
+        assert_eq!(scripted.timeline(), model.timeline());
+        for i in 0..=40 {
+            let time = model.duration() * i as f32 / 40.0;
+            let expected = model.get_model(time);
+            let actual = scripted.get_model(time);
+            assert_eq!(actual.triangles, expected.triangles);
+            assert_eq!(actual.lines, expected.lines);
+            assert_eq!(scripted.step_at(time), model.step_at(time));
+            for (a, b) in actual.vertices.iter().zip(expected.vertices.iter()) {
+                assert!((a - b).norm() < 1e-5, "time {}: {} != {}", time, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_fold() {
+        let geometry = PlaneGeometry::new();
+        let json = export(&geometry.get_model(0.0), &geometry.get_model(0.5)).unwrap();
+        let folded = FoldPattern::from_json_frame(&json, 1).unwrap();
+        assert_eq!(folded.model.vertices, geometry.get_model(0.5).vertices);
+        assert_eq!(folded.model.triangles, geometry.get_model(0.5).triangles);
+    }
+
+    #[test]
+    fn test_crease_pattern_svg() {
+        let svg = PlaneGeometry::new().crease_pattern_svg().unwrap();
+        assert!(svg.contains(r#"width="210mm" height="297mm""#));
+        assert!(svg.contains(
+            r#"<line class="valley" x1="105.000" y1="0.000" x2="210.000" y2="105.000"/>"#
+        ));
+        assert!(svg
+            .contains(r#"<line class="valley" x1="105.000" y1="0.000" x2="0.000" y2="105.000"/>"#));
+        // The unfolded pre-crease of the centre line is drawn once, as the first valley
+        assert_eq!(svg.matches("<text").count(), 14);
+        assert!(svg.contains(">7</text>"));
+        assert_eq!(svg.matches(r#"class="mountain""#).count(), 2);
+        // Creases run across the sheet, not between the vertices of the fold line only
+        assert!(svg.contains(
+            r#"<line class="valley" x1="105.000" y1="90.013" x2="210.000" y2="211.064"/>"#
+        ));
 
-        model
+        let pattern = FoldPattern::from_svg(&svg, 0.01).unwrap();
+        assert!(pattern.assignments.contains(&EdgeAssignment::Valley));
+        assert!(pattern.assignments.contains(&EdgeAssignment::Border));
     }
 }
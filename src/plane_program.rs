@@ -1,6 +1,7 @@
+use std::cell::RefCell;
 use std::iter::once;
 
-use crate::model::Model;
+use crate::model::{Crease, Model};
 use crate::scene::Scene;
 use glm::{Mat4, Vec3};
 use webgl_rc::{
@@ -14,12 +15,32 @@ pub struct PlaneProgram {
     lines_program: Program,
     lines_array: ItemsBuffer<LineVertex>,
     triangles_array: ItemsBuffer<TriangleVertex>,
+    /// Contents of the arrays, kept to reuse the memory in every frame
+    vertices: RefCell<Vec<Vec3>>,
+    triangles: RefCell<Vec<TriangleVertex>>,
+    lines: RefCell<Vec<LineVertex>>,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Attributes)]
 struct LineVertex {
     position: Vec3,
     opacity: f32,
+    color: Vec3,
+    /// Distance from the start of the line, for dash patterns
+    distance: f32,
+    /// Dash pattern, see `plane-line.f.glsl`
+    dash: f32,
+}
+
+/// Colour, dash pattern and opacity of lines of the crease type
+fn line_style(crease: Crease) -> (Vec3, f32, f32) {
+    match crease {
+        Crease::Mountain => (Vec3::new(0.8, 0.15, 0.1), 2.0, 1.0),
+        Crease::Valley => (Vec3::new(0.1, 0.3, 0.85), 1.0, 1.0),
+        Crease::Border => (Vec3::new(0.6, 0.6, 0.6), 0.0, 1.0),
+        Crease::Cut => (Vec3::new(0.1, 0.6, 0.2), 0.0, 1.0),
+        Crease::Guide => (Vec3::new(0.6, 0.6, 0.6), 3.0, 0.5),
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Uniforms)]
@@ -59,66 +80,87 @@ impl PlaneProgram {
             triangles_program,
             lines_array,
             triangles_array,
+            vertices: RefCell::new(vec![]),
+            triangles: RefCell::new(vec![]),
+            lines: RefCell::new(vec![]),
         })
     }
 
-    pub fn draw(&self, scene: &Scene, model: &Model) {
+    /// Draw the model with every face moved by its offset in `offsets`,
+    /// so stacked layers of paper don't fight for depth
+    pub fn draw(&self, scene: &Scene, model: &Model, offsets: &[Vec3]) {
         let camera = &scene.camera;
         let plane_matrix = camera.get_projection_matrix() * camera.get_view_matrix();
         let light_position = scene.light_position;
 
-        let vertices: Vec<_> = model
-            .vertices
-            .iter()
-            .map(|v| scene.model_matrix * v.push(1.0))
-            .map(|v| v.xyz())
-            .collect();
-
-        let triangles: Vec<_> = model
-            .triangles
-            .iter()
-            .copied()
-            .map(|(a, b, c)| {
-                let p1 = vertices[a as usize];
-                let p2 = vertices[b as usize];
-                let p3 = vertices[c as usize];
-                (p1, p2, p3)
-            })
-            .flat_map(|(p1, p2, p3)| {
-                let normal: Vec3 = (p2 - p1).cross(&(p3 - p1)).normalize();
-                once(TriangleVertex {
-                    position: p1,
-                    normal,
+        let mut vertices = self.vertices.borrow_mut();
+        vertices.clear();
+        vertices.extend(
+            model
+                .vertices
+                .iter()
+                .map(|v| scene.model_matrix * v.push(1.0))
+                .map(|v| v.xyz()),
+        );
+
+        let mut triangles = self.triangles.borrow_mut();
+        triangles.clear();
+        triangles.extend(
+            model
+                .triangles
+                .iter()
+                .copied()
+                .enumerate()
+                .map(|(face, (a, b, c))| {
+                    let offset = offsets
+                        .get(face)
+                        .map(|offset| (scene.model_matrix * offset.push(0.0)).xyz())
+                        .unwrap_or_else(Vec3::zeros);
+                    let p1 = vertices[a as usize] + offset;
+                    let p2 = vertices[b as usize] + offset;
+                    let p3 = vertices[c as usize] + offset;
+                    (p1, p2, p3)
                 })
-                .chain(once(TriangleVertex {
-                    position: p2,
-                    normal,
-                }))
-                .chain(once(TriangleVertex {
-                    position: p3,
-                    normal,
-                }))
-            })
-            .collect();
+                .flat_map(|(p1, p2, p3)| {
+                    let normal: Vec3 = (p2 - p1).cross(&(p3 - p1)).normalize();
+                    once(TriangleVertex {
+                        position: p1,
+                        normal,
+                    })
+                    .chain(once(TriangleVertex {
+                        position: p2,
+                        normal,
+                    }))
+                    .chain(once(TriangleVertex {
+                        position: p3,
+                        normal,
+                    }))
+                }),
+        );
 
         self.triangles_array
             .set_content(&triangles, BufferUsage::Dynamic);
 
-        let lines: Vec<_> = model
-            .lines
-            .iter()
-            .copied()
-            .flat_map(|(p1, p2, a)| {
-                once(LineVertex {
-                    position: vertices[p1 as usize],
-                    opacity: a,
-                })
-                .chain(once(LineVertex {
-                    position: vertices[p2 as usize],
-                    opacity: a,
-                }))
+        let mut lines = self.lines.borrow_mut();
+        lines.clear();
+        lines.extend(model.lines.iter().copied().flat_map(|(p1, p2, a, crease)| {
+            let (color, dash, opacity) = line_style(crease);
+            let (p1, p2) = (vertices[p1 as usize], vertices[p2 as usize]);
+            once(LineVertex {
+                position: p1,
+                opacity: a * opacity,
+                color,
+                distance: 0.0,
+                dash,
             })
-            .collect();
+            .chain(once(LineVertex {
+                position: p2,
+                opacity: a * opacity,
+                color,
+                distance: (p2 - p1).norm(),
+                dash,
+            }))
+        }));
 
         self.lines_array.set_content(&lines, BufferUsage::Dynamic);
 